@@ -1,16 +1,20 @@
 (function() {
   const codeSegment = document.getElementById('diagonal-code-segment')
   const writingSegment = document.getElementById('diagonal-writing-segment')
+  const transcriptLink = document.getElementById('transcript-link')
 
-  const segmentMaxLines = 35
-  const codeSegmentTop = [3, 5]            // Start drawing code from x=3, y=5
-  const segmentIdentLineDelta = [6, 3] // Add x+6, y+3 to indent the next line of code
+  // Window geometry comes from the `[animation]` settings in site.toml.
+  const segmentMaxLines = SITE_CONFIG.animation.windowLines
+  const codeSegmentTop = SITE_CONFIG.animation.codeOrigin         // Start drawing code from here
+  const writingSegmentTop = SITE_CONFIG.animation.writingOrigin   // and writing from here
+  const segmentIdentLineDelta = SITE_CONFIG.animation.lineDelta   // Add x, y to indent the next line of code
 
   // Produce an HTML <text> element positioned at the given coordinates.
   const text = (x, y, className, text) => {
     let newNode = document.createElementNS('http://www.w3.org/2000/svg', 'text');
     newNode.setAttribute('x', `${x}`)
     newNode.setAttribute('y', `${y}`)
+    newNode.setAttribute('aria-hidden', 'true')
     newNode.textContent = text
 
     newNode.classList.add(className)
@@ -28,8 +32,8 @@
   }
 
   const writing = (lineNumber, lineText) => {
-    const startX = 5 + 0.5 * lineNumber * segmentIdentLineDelta[0]
-    const startY = 98 - lineNumber * segmentIdentLineDelta[1]
+    const startX = writingSegmentTop[0] + 0.5 * lineNumber * segmentIdentLineDelta[0]
+    const startY = writingSegmentTop[1] - lineNumber * segmentIdentLineDelta[1]
   
     return text(startX, startY, 'writing', lineText)
   }
@@ -237,29 +241,108 @@
     return animator
   }
 
+  // The server draws the first frame, so the animation carries on from the
+  // source and sample it drew, taking over its nodes.
+  const servedSource = () => {
+    const snippet = CODE_SNIPPETS.find(s => s.languageName === codeSegment.dataset.language)
+    const source = snippet && snippet.sourceFiles.find(s => s.fileName === codeSegment.dataset.file)
+    return source && { snippet, source }
+  }
+
+  const servedSample = () =>
+    WRITING_SAMPLES.find(s => s.slug === writingSegment.dataset.slug)
+
+  const adoptCode = animator => {
+    const { animator: a } = _codeAnimatorStep(animator)
+
+    return {
+      animator: a,
+      nodes: Array.from(codeSegment.getElementsByClassName('code')),
+    }
+  }
+
+  const adoptWriting = animator => {
+    const { animator: a } = _writingAnimatorStep(animator)
+
+    return {
+      animator: a,
+      nodes: Array.from(writingSegment.getElementsByClassName('writing')),
+    }
+  }
+
+  // What's showing, for the transcript. Animators change in place as they
+  // step, so these follow along.
+  const showing = {
+    code: null,
+    writing: null,
+  }
+
+  const transcriptUrl = () => {
+    const query = new URLSearchParams()
+    if (showing.code !== null) {
+      query.set('language', showing.code._language)
+      query.set('file', showing.code._source.fileName)
+      query.set('code_line', Math.max(0, showing.code._startLine - segmentMaxLines))
+    }
+    if (showing.writing !== null) {
+      query.set('slug', showing.writing._sample.slug)
+      query.set('writing_line', Math.max(0, showing.writing._startLine - segmentMaxLines))
+    }
+    return `/transcript?${query}`
+  }
+
+  // Motion can be turned off with `?motion=reduce`, which the server also
+  // remembers, or by the visitor's system settings unless they've asked for
+  // it with `?motion=full`. Either way the server's first frame stays put.
+  const reduceMotion = () => {
+    const chosen = new URLSearchParams(window.location.search).get('motion') || document.getElementById('content').dataset.motion
+    if (chosen === 'reduce' || chosen === 'full') {
+      return chosen === 'reduce'
+    }
+    return window.matchMedia !== undefined && window.matchMedia('(prefers-reduced-motion: reduce)').matches
+  }
+
+  // Themes scroll at their own pace, set in `css/themes.css`.
+  const stepMillis = () => {
+    const speed = parseFloat(getComputedStyle(document.documentElement).getPropertyValue('--animation-speed'))
+    return speed > 0 ? SITE_CONFIG.animation.stepMillis / speed : SITE_CONFIG.animation.stepMillis
+  }
+
   const pickRandom = list => {
     const index = Math.floor(Math.random() * list.length)
     return list[index]
   }
 
   const main = () => {
-    const snippet = pickRandom(CODE_SNIPPETS)
-    const source = pickRandom(snippet.sourceFiles)
+    if (reduceMotion()) {
+      return
+    }
+    transcriptLink.addEventListener('click', () => {
+      transcriptLink.href = transcriptUrl()
+    })
+
+    const served = servedSource()
+    const snippet = served ? served.snippet : pickRandom(CODE_SNIPPETS)
+    const source = served ? served.source : pickRandom(snippet.sourceFiles)
 
     let codeA = codeAnimator(snippet.languageName, source)
-    let { animator: newCodeA, nodes: codeNodes } = drawAllCode(codeA)
+    showing.code = codeA
+    let { animator: newCodeA, nodes: codeNodes } = served ? adoptCode(codeA) : drawAllCode(codeA)
 
-    let sched = schedule({ animator: newCodeA, nodes: codeNodes }, 200, [ drawCode, prepareCodeAnimator ])
+    let sched = schedule({ animator: newCodeA, nodes: codeNodes }, stepMillis(), [ drawCode, prepareCodeAnimator ])
 
     _run(sched)
 
 
-    const sample = pickRandom(WRITING_SAMPLES)
+    const sample = servedSample() || pickRandom(WRITING_SAMPLES)
 
     let writingA = writingAnimator(sample)
-    let { animator: newWritingA, nodes: writingNodes } = drawAllWriting(writingA)
+    showing.writing = writingA
+    let { animator: newWritingA, nodes: writingNodes } = sample === servedSample()
+      ? adoptWriting(writingA)
+      : drawAllWriting(writingA)
 
-    sched = schedule({ animator: newWritingA, nodes: writingNodes }, 200, [ drawWriting, prepareWritingAnimator ])
+    sched = schedule({ animator: newWritingA, nodes: writingNodes }, stepMillis(), [ drawWriting, prepareWritingAnimator ])
 
     _run(sched)
   }
@@ -0,0 +1,37 @@
+use rocket::State;
+use rocket::response::content::Json;
+use serde_json;
+
+use search::Index;
+use writing::Sample;
+
+
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+#[get("/api/writing?<min_words>&<max_words>")]
+pub fn writing(
+  min_words: Option<usize>,
+  max_words: Option<usize>,
+  samples: State<Vec<Sample>>,
+) -> Json<String> {
+  let selected = samples
+    .iter()
+    .filter(|sample| match min_words {
+      Some(min) => sample.stats.word_count >= min,
+      None      => true,
+    })
+    .filter(|sample| match max_words {
+      Some(max) => sample.stats.word_count <= max,
+      None      => true,
+    })
+    .collect::<Vec<&Sample>>();
+
+  Json(serde_json::to_string(&selected).unwrap())
+}
+
+#[get("/search?<q>&<limit>")]
+pub fn search(q: String, limit: Option<usize>, index: State<Index>) -> Json<String> {
+  let hits = index.search(&q, limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+
+  Json(serde_json::to_string(&hits).unwrap())
+}
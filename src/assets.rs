@@ -0,0 +1,128 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rocket::{Data, Request, Route, State};
+use rocket::handler::{Handler, Outcome};
+use rocket::http::{ContentType, Method, Status};
+use rocket::http::uri::Segments;
+use rocket::response::NamedFile;
+use rocket::response::content::Content;
+
+use caching::LastModified;
+use compression::{self, Encoded, Encoding};
+use fingerprint::Manifest;
+
+
+// Directories served as-is, each mounted under its own name.
+pub const ROOTS: &[&str] = &["css", "js", "fonts"];
+// Where the fonts that themes use are served from.
+pub const FONTS: &str = "fonts";
+
+// Let every other route have a go before falling back to asset roots.
+const RANK: isize = 10;
+
+#[derive(Clone)]
+pub struct AssetRoot {
+  name: String,
+  dir: PathBuf,
+}
+
+impl AssetRoot {
+  pub fn new(name: &str) -> Result<AssetRoot, io::Error> {
+    Ok(AssetRoot {
+      name: name.to_string(),
+      dir: Path::new(name).canonicalize()?,
+    })
+  }
+
+  // Map request path segments to a file inside this root. Segments are
+  // validated like Rocket's `PathBuf` (no `..`, no dotfiles) and the result
+  // is canonicalized so a symlink can't lead outside of the root.
+  // Fingerprinted names are looked up in the manifest, so a stale hash is
+  // never served with the current content.
+  fn resolve(&self, segments: Segments, manifest: Option<&Manifest>) -> Option<PathBuf> {
+    let mut relative = segments.into_path_buf(false).ok()?;
+
+    let fingerprinted = format!("{}/{}", self.name, relative.to_str()?.replace('\\', "/"));
+    if let Some(logical) = manifest.and_then(|manifest| manifest.logical(&fingerprinted)) {
+      relative = Path::new(logical).strip_prefix(&self.name).ok()?.to_path_buf();
+    }
+
+    let path = self.dir.join(relative).canonicalize().ok()?;
+
+    if path.starts_with(&self.dir) && path.is_file() {
+      Some(path)
+    } else {
+      None
+    }
+  }
+}
+
+impl Handler for AssetRoot {
+  fn handle<'r>(&self, request: &'r Request, data: Data) -> Outcome<'r> {
+    let manifest = request.guard::<State<Manifest>>().succeeded();
+    let path = request
+      .get_segments::<Segments>(0)
+      .and_then(Result::ok)
+      .and_then(|segments| self.resolve(segments, manifest.as_ref().map(|manifest| manifest.inner())));
+
+    let path = match path {
+      Some(path) => path,
+      // Let the redirect table have a go at assets that have moved.
+      None       => return Outcome::forward(data),
+    };
+
+    let content_type = content_type(&path);
+    let modified = match fs::metadata(&path).and_then(|meta| meta.modified()) {
+      Ok(modified) => modified,
+      Err(_)       => return Outcome::failure(Status::NotFound),
+    };
+
+    if !compression::is_compressible(&path) {
+      return match NamedFile::open(&path) {
+        Ok(file) => Outcome::from(request, LastModified(Content(content_type, file), modified)),
+        Err(_)   => Outcome::failure(Status::NotFound),
+      };
+    }
+
+    // Prefer a precompressed sibling, leaving anything else to be compressed
+    // on the fly.
+    let encoding = Encoding::negotiate(request);
+    let (path, encoding) = match compression::sibling(&path, encoding) {
+      Some(sibling) => (sibling, encoding),
+      None          => (path, Encoding::Identity),
+    };
+
+    match NamedFile::open(path) {
+      Ok(file) => Outcome::from(request, Encoded(LastModified(Content(content_type, file), modified), encoding)),
+      Err(_)   => Outcome::failure(Status::NotFound),
+    }
+  }
+}
+
+impl From<AssetRoot> for Vec<Route> {
+  fn from(root: AssetRoot) -> Vec<Route> {
+    vec![Route::ranked(RANK, Method::Get, "/<path..>", root)]
+  }
+}
+
+pub fn content_type(path: &Path) -> ContentType {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or("")
+    .to_lowercase();
+
+  match extension.as_str() {
+    "woff"  => ContentType::new("font", "woff"),
+    "woff2" => ContentType::new("font", "woff2"),
+    "ttf"   => ContentType::new("font", "ttf"),
+    "otf"   => ContentType::new("font", "otf"),
+    "svg"   => ContentType::SVG,
+    "ico"   => ContentType::Icon,
+    "avif"  => ContentType::new("image", "avif"),
+    "map"   => ContentType::JSON,
+    _       => ContentType::from_extension(&extension).unwrap_or(ContentType::Binary),
+  }
+}
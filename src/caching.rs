@@ -0,0 +1,185 @@
+use std::fmt::Write;
+use std::io::Cursor;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use rocket::{Request, Response, Rocket, State};
+use rocket::config::Config;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Method, Status};
+use rocket::response::{self, Responder};
+use sha2::{Digest, Sha256};
+
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+const DEFAULT_POLICY: &str = "no-cache";
+const FINGERPRINTED_POLICY: &str = "public, max-age=31536000, immutable";
+
+// `Cache-Control` values chosen by request path, configured in Rocket.toml:
+//
+//   [global.cache_control]
+//   default = "no-cache"
+//   fingerprinted = "public, max-age=31536000, immutable"
+//   "/css/" = "public, max-age=3600"
+pub struct CachePolicy {
+  default: String,
+  fingerprinted: String,
+  prefixes: Vec<(String, String)>,
+}
+
+impl CachePolicy {
+  pub fn from_config(config: &Config) -> Result<CachePolicy, String> {
+    let mut policy = CachePolicy {
+      default: DEFAULT_POLICY.to_string(),
+      fingerprinted: FINGERPRINTED_POLICY.to_string(),
+      prefixes: Vec::new(),
+    };
+
+    let table = match config.get_table("cache_control") {
+      Ok(table) => table,
+      Err(_)    => return Ok(policy),
+    };
+    for (key, value) in table {
+      let value = value
+        .as_str()
+        .ok_or_else(|| format!("cache_control.{} must be a string", key))?
+        .to_string();
+
+      match key.as_str() {
+        "default"       => policy.default = value,
+        "fingerprinted" => policy.fingerprinted = value,
+        prefix if prefix.starts_with('/') => policy.prefixes.push((prefix.to_string(), value)),
+        _               => return Err(format!("cache_control.{} must be `default`, `fingerprinted` or a path", key)),
+      }
+    }
+    Ok(policy)
+  }
+
+  pub fn for_path(&self, path: &str) -> &str {
+    if is_fingerprinted(path) {
+      return &self.fingerprinted;
+    }
+
+    longest_prefix(&self.prefixes, path).unwrap_or(&self.default)
+  }
+}
+
+// The value kept for the longest of `prefixes` that `path` starts with, so the
+// most specific wins when several match.
+pub fn longest_prefix<'a, T>(prefixes: &'a [(String, T)], path: &str) -> Option<&'a T> {
+  prefixes
+    .iter()
+    .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+    .max_by_key(|(prefix, _)| prefix.len())
+    .map(|(_, value)| value)
+}
+
+// Adds strong ETags and `Cache-Control` to successful GET responses and
+// turns them into `304 Not Modified` when the client's copy is current.
+pub struct Caching;
+
+impl Fairing for Caching {
+  fn info(&self) -> Info {
+    Info {
+      name: "HTTP caching",
+      kind: Kind::Attach | Kind::Response,
+    }
+  }
+
+  fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+    match CachePolicy::from_config(rocket.config()) {
+      Ok(policy) => Ok(rocket.manage(policy)),
+      Err(err)   => {
+        eprintln!("Invalid cache_control configuration: {}", err);
+        Err(rocket)
+      },
+    }
+  }
+
+  fn on_response(&self, request: &Request, response: &mut Response) {
+    let method = request.method();
+    if (method != Method::Get && method != Method::Head) || response.status() != Status::Ok {
+      return;
+    }
+
+    if !response.headers().contains("Cache-Control") {
+      if let Some(policy) = request.guard::<State<CachePolicy>>().succeeded() {
+        response.set_raw_header("Cache-Control", policy.for_path(request.uri().path()).to_string());
+      }
+    }
+
+    let body = match response.body_bytes() {
+      Some(body) => body,
+      None       => return,
+    };
+    let etag = format!("\"{}\"", &content_hash(&body)[..32]);
+    response.set_raw_header("ETag", etag.clone());
+
+    if is_not_modified(request, response, &etag) {
+      response.set_status(Status::NotModified);
+      response.remove_header("Content-Type");
+    } else {
+      response.set_sized_body(Cursor::new(body));
+    }
+  }
+}
+
+// Sets `Last-Modified` on a response, for content backed by a file.
+pub struct LastModified<R>(pub R, pub SystemTime);
+
+impl<'r, R: Responder<'r>> Responder<'r> for LastModified<R> {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = self.0.respond_to(request)?;
+    response.set_raw_header("Last-Modified", http_date(self.1));
+    Ok(response)
+  }
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+  let digest = Sha256::digest(bytes);
+  let mut hash = String::with_capacity(digest.len() * 2);
+  for byte in digest.iter() {
+    write!(hash, "{:02x}", byte).unwrap();
+  }
+  hash
+}
+
+pub fn http_date(time: SystemTime) -> String {
+  DateTime::<Utc>::from(time).format(HTTP_DATE_FORMAT).to_string()
+}
+
+// Fingerprinted file names carry a content hash before their extension,
+// as in `main.3f2a9c1b04e5d6f7.css`.
+pub fn is_fingerprinted(path: &str) -> bool {
+  let file_name = path.rsplit('/').next().unwrap_or("");
+  let parts = file_name.split('.').collect::<Vec<&str>>();
+
+  parts.len() >= 3 && {
+    let hash = parts[parts.len() - 2];
+    hash.len() >= 8 && hash.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+  }
+}
+
+// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 7232 §6).
+fn is_not_modified(request: &Request, response: &Response, etag: &str) -> bool {
+  if let Some(tags) = request.headers().get_one("If-None-Match") {
+    return tags
+      .split(',')
+      .map(|tag| tag.trim().trim_start_matches("W/"))
+      .any(|tag| tag == "*" || tag == etag);
+  }
+
+  let since = request
+    .headers()
+    .get_one("If-Modified-Since")
+    .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+  let modified = response
+    .headers()
+    .get_one("Last-Modified")
+    .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+
+  match (since, modified) {
+    (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+    _                             => false,
+  }
+}
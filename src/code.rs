@@ -2,6 +2,8 @@ use std::fs;
 use std::io::{self, Read};
 use std::path::Path;
 
+use chrono::{DateTime, Utc};
+
 
 #[derive(Debug, Serialize)]
 pub struct Line {
@@ -16,6 +18,9 @@ pub struct Source {
   pub file_name: String,
   #[serde(rename = "linesOfCode")]
   pub lines_of_code: Vec<Line>,
+  // When the snippet file was last modified.
+  #[serde(skip)]
+  pub updated: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +33,37 @@ pub struct SourceCodeDirectory {
 
 pub type AnalysisResult = Result<Vec<SourceCodeDirectory>, io::Error>;
 
+#[derive(Debug, PartialEq)]
+pub struct Provenance {
+  pub owner: String,
+  pub repository: String,
+  pub path: String,
+}
+
+impl Provenance {
+  pub fn url(&self) -> String {
+    format!("https://github.com/{}/{}", self.owner, self.repository)
+  }
+}
+
+impl Source {
+  // Snippets are named after where they were taken from, with path separators
+  // replaced by dashes: `arcrose-clink-src-links-tags.rs` is `src/links/tags.rs`
+  // from the `arcrose/clink` repository.
+  pub fn provenance(&self) -> Option<Provenance> {
+    let mut parts = self.file_name.splitn(3, '-');
+
+    match (parts.next(), parts.next(), parts.next()) {
+      (Some(owner), Some(repository), Some(path)) => Some(Provenance {
+        owner: owner.to_string(),
+        repository: repository.to_string(),
+        path: path.replace('-', "/"),
+      }),
+      _ => None,
+    }
+  }
+}
+
 pub fn analyze<P: AsRef<Path>>(directory: P) -> AnalysisResult {
   let dir_contents = fs::read_dir(directory.as_ref())?;
   let directories = dir_contents
@@ -58,6 +94,7 @@ pub fn analyze<P: AsRef<Path>>(directory: P) -> AnalysisResult {
       let source = process(file)?;
       sources.push(source);
     }
+    sources.sort_by(|a, b| a.file_name.cmp(&b.file_name));
 
     source_dirs.push(SourceCodeDirectory {
       language_name: language,
@@ -65,6 +102,7 @@ pub fn analyze<P: AsRef<Path>>(directory: P) -> AnalysisResult {
     });
   }
 
+  source_dirs.sort_by(|a, b| a.language_name.cmp(&b.language_name));
   Ok(source_dirs)
 }
 
@@ -74,6 +112,7 @@ enum IndentCounterSM {
   },
   Processing {
     file_name: String,
+    updated: DateTime<Utc>,
     lines: Vec<Line>,
     processing_index: usize,
     indent_guess: &'static str,
@@ -92,13 +131,14 @@ fn process<P: AsRef<Path>>(file_path: P) -> Result<Source, io::Error> {
     .unwrap()
     .to_string();
   let mut source_code = String::new();
-  fs::File::open(file_path)
-    ?.read_to_string(&mut source_code)?;
+  let mut file = fs::File::open(file_path)?;
+  file.read_to_string(&mut source_code)?;
+  let updated = DateTime::from(file.metadata()?.modified()?);
 
   let mut state = Start{ source_code };
   loop {
     state = match state {
-      Start{ source_code } => tokenize(file_name.clone(), source_code),
+      Start{ source_code } => tokenize(file_name.clone(), updated, source_code),
       p@Processing{ .. }   => proceed(p),
       Finished(source)     => return Ok(source),
     }
@@ -108,6 +148,7 @@ fn process<P: AsRef<Path>>(file_path: P) -> Result<Source, io::Error> {
 fn proceed(state: IndentCounterSM) -> IndentCounterSM {
   if let IndentCounterSM::Processing{
     file_name,
+    updated,
     mut lines,
     processing_index,
     indent_guess,
@@ -117,6 +158,7 @@ fn proceed(state: IndentCounterSM) -> IndentCounterSM {
       IndentCounterSM::Finished(Source {
         file_name,
         lines_of_code: lines,
+        updated,
       })
     } else {
       let (new_line, new_indent_guess) = update_indent_guess(&lines[processing_index], indent_guess);
@@ -124,6 +166,7 @@ fn proceed(state: IndentCounterSM) -> IndentCounterSM {
 
       IndentCounterSM::Processing {
         file_name,
+        updated,
         lines,
         processing_index: processing_index + 1,
         indent_guess: new_indent_guess,
@@ -168,7 +211,7 @@ fn update_indent_guess(line: &Line, indent_guess: &'static str) -> (Line, &'stat
   }
 }
 
-fn tokenize(file_name: String, source_code: String) -> IndentCounterSM {
+fn tokenize(file_name: String, updated: DateTime<Utc>, source_code: String) -> IndentCounterSM {
   let mut lines = Vec::new();
   let mut line_no = 0;
 
@@ -183,6 +226,7 @@ fn tokenize(file_name: String, source_code: String) -> IndentCounterSM {
 
   IndentCounterSM::Processing {
     file_name,
+    updated,
     lines,
     processing_index: 0,
     indent_guess: "\t",
@@ -0,0 +1,274 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use brotli::CompressorWriter;
+use flate2::Compression as GzipLevel;
+use flate2::write::GzEncoder;
+use rocket::{Request, Response};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::response::{self, Responder};
+
+
+// Smaller bodies gain little and can even grow once compressed.
+const MIN_SIZE: usize = 1024;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_WINDOW: u32 = 22;
+// Precompressed files are written once, so they can take the slowest,
+// smallest settings. Responses compressed per request cannot.
+const STATIC_QUALITY: u32 = 11;
+const DYNAMIC_QUALITY: u32 = 5;
+
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &["css", "html", "js", "json", "map", "svg", "txt", "xml"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+  Brotli,
+  Gzip,
+  Identity,
+}
+
+impl Encoding {
+  pub fn name(self) -> &'static str {
+    match self {
+      Encoding::Brotli   => "br",
+      Encoding::Gzip     => "gzip",
+      Encoding::Identity => "identity",
+    }
+  }
+
+  fn extension(self) -> Option<&'static str> {
+    match self {
+      Encoding::Brotli   => Some("br"),
+      Encoding::Gzip     => Some("gz"),
+      Encoding::Identity => None,
+    }
+  }
+
+  // Choose the best encoding the client accepts, preferring Brotli when the
+  // client weighs both equally.
+  pub fn negotiate(request: &Request) -> Encoding {
+    let accept = request.headers().get_one("Accept-Encoding").unwrap_or("");
+    let mut best = (Encoding::Identity, 0.0);
+
+    for (encoding, quality) in accepted(accept) {
+      if quality > best.1 {
+        best = (encoding, quality);
+      }
+    }
+
+    best.0
+  }
+
+  pub fn compress(self, bytes: &[u8], quality: u32) -> Result<Vec<u8>, io::Error> {
+    match self {
+      Encoding::Brotli   => {
+        let mut compressed = Vec::new();
+        {
+          let mut writer = CompressorWriter::new(&mut compressed, BROTLI_BUFFER_SIZE, quality, BROTLI_WINDOW);
+          writer.write_all(bytes)?;
+        }
+        Ok(compressed)
+      },
+      Encoding::Gzip     => {
+        let mut encoder = GzEncoder::new(Vec::new(), GzipLevel::new(quality.min(9)));
+        encoder.write_all(bytes)?;
+        encoder.finish()
+      },
+      Encoding::Identity => Ok(bytes.to_vec()),
+    }
+  }
+}
+
+// Compresses dynamic responses, such as the JSON API and rendered pages, that
+// weren't served from a precompressed file.
+pub struct Compression;
+
+impl Fairing for Compression {
+  fn info(&self) -> Info {
+    Info {
+      name: "Response compression",
+      kind: Kind::Response,
+    }
+  }
+
+  fn on_response(&self, request: &Request, response: &mut Response) {
+    let compressible = response
+      .content_type()
+      .map(|content_type| is_compressible_type(&content_type))
+      .unwrap_or(false);
+    if !compressible || response.headers().contains("Content-Encoding") {
+      return;
+    }
+    add_vary(response, "Accept-Encoding");
+
+    let encoding = Encoding::negotiate(request);
+    if encoding == Encoding::Identity {
+      return;
+    }
+
+    let body = match response.body_bytes() {
+      Some(body) => body,
+      None       => return,
+    };
+    if body.len() >= MIN_SIZE {
+      if let Ok(compressed) = encoding.compress(&body, DYNAMIC_QUALITY) {
+        response.set_raw_header("Content-Encoding", encoding.name());
+        response.set_sized_body(Cursor::new(compressed));
+        return;
+      }
+    }
+    response.set_sized_body(Cursor::new(body));
+  }
+}
+
+// Marks a response whose body is already in the given encoding.
+pub struct Encoded<R>(pub R, pub Encoding);
+
+impl<'r, R: Responder<'r>> Responder<'r> for Encoded<R> {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = self.0.respond_to(request)?;
+    add_vary(&mut response, "Accept-Encoding");
+    if self.1 != Encoding::Identity {
+      response.set_raw_header("Content-Encoding", self.1.name());
+    }
+    Ok(response)
+  }
+}
+
+// Write `.br` and `.gz` siblings next to every compressible file in the asset
+// roots, skipping those that are already up to date.
+pub fn precompress(roots: &[&str]) -> Result<(), io::Error> {
+  for root in roots {
+    precompress_dir(Path::new(root))?;
+  }
+
+  Ok(())
+}
+
+// The newest precompressed sibling of `path` for the encoding, if there is one.
+pub fn sibling(path: &Path, encoding: Encoding) -> Option<PathBuf> {
+  let sibling = sibling_path(path, encoding.extension()?);
+
+  if is_fresh(path, &sibling) {
+    Some(sibling)
+  } else {
+    None
+  }
+}
+
+pub fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+  let mut name = OsString::from(path.as_os_str());
+  name.push(".");
+  name.push(extension);
+  PathBuf::from(name)
+}
+
+pub fn is_precompressed(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension == "br" || extension == "gz")
+    .unwrap_or(false)
+}
+
+pub fn is_compressible(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| COMPRESSIBLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+    .unwrap_or(false)
+}
+
+// Caches between the server and the client must keep the variants apart.
+pub fn add_vary(response: &mut Response, header: &str) {
+  let vary = match response.headers().get_one("Vary") {
+    Some(vary) if vary.split(',').any(|field| field.trim().eq_ignore_ascii_case(header)) => return,
+    Some(vary) => format!("{}, {}", vary, header),
+    None       => header.to_string(),
+  };
+  response.set_raw_header("Vary", vary);
+}
+
+fn precompress_dir(dir: &Path) -> Result<(), io::Error> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+
+    if path.is_dir() {
+      precompress_dir(&path)?;
+    } else if is_compressible(&path) {
+      let mut content = None;
+
+      for &encoding in &[Encoding::Brotli, Encoding::Gzip] {
+        if sibling(&path, encoding).is_some() {
+          continue;
+        }
+        if content.is_none() {
+          content = Some(fs::read(&path)?);
+        }
+        let compressed = encoding.compress(content.as_ref().unwrap(), STATIC_QUALITY)?;
+        fs::write(sibling_path(&path, encoding.extension().unwrap()), compressed)?;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn is_fresh(path: &Path, sibling: &Path) -> bool {
+  let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+
+  match (modified(path), modified(sibling)) {
+    (Some(original), Some(compressed)) => compressed >= original,
+    _                                  => false,
+  }
+}
+
+fn is_compressible_type(content_type: &ContentType) -> bool {
+  let (top, sub) = (content_type.top().as_str(), content_type.sub().as_str());
+
+  top == "text"
+    || sub == "json"
+    || sub == "javascript"
+    || sub == "xml"
+    || sub.ends_with("+xml")
+    || sub.ends_with("+json")
+}
+
+// Encodings listed in an `Accept-Encoding` header with their weights. An
+// unlisted encoding is refused unless `*` is.
+fn accepted(header: &str) -> Vec<(Encoding, f32)> {
+  let mut weights = Vec::new();
+  let mut wildcard = None;
+
+  for item in header.split(',') {
+    let mut parts = item.split(';');
+    let coding = parts.next().unwrap_or("").trim().to_lowercase();
+    let quality = parts
+      .map(str::trim)
+      .find(|param| param.starts_with("q="))
+      .and_then(|param| param[2..].trim().parse::<f32>().ok())
+      .unwrap_or(1.0);
+
+    match coding.as_str() {
+      "br"              => weights.push((Encoding::Brotli, quality)),
+      "gzip" | "x-gzip" => weights.push((Encoding::Gzip, quality)),
+      "*"               => wildcard = Some(quality),
+      _                 => (),
+    }
+  }
+
+  if let Some(quality) = wildcard {
+    for &encoding in &[Encoding::Brotli, Encoding::Gzip] {
+      if !weights.iter().any(|&(listed, _)| listed == encoding) {
+        weights.push((encoding, quality));
+      }
+    }
+  }
+  // Stable sort keeps Brotli ahead of gzip when their weights tie.
+  weights.sort_by_key(|&(encoding, _)| encoding != Encoding::Brotli);
+
+  weights
+}
@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use toml::Value;
+
+use assets;
+
+
+pub const DEFAULT_FILE: &str = "site.toml";
+// `HOMUPAGE_CONFIG` names another file; any other variable with this prefix
+// overrides one setting, as in `HOMUPAGE_SITE_URL=http://localhost:8000`.
+pub const ENV_PREFIX: &str = "HOMUPAGE_";
+const CONFIG_FILE_VAR: &str = "HOMUPAGE_CONFIG";
+
+const MAX_WINDOW_LINES: u32 = 200;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SiteConfig {
+  pub site: Site,
+  pub paths: Paths,
+  pub animation: Animation,
+  pub theme: ThemeChoice,
+  pub fonts: BTreeMap<String, Font>,
+  pub themes: BTreeMap<String, Theme>,
+  #[serde(default)]
+  pub robots: Robots,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Site {
+  pub title: String,
+  pub author: String,
+  pub url: String,
+  pub description: String,
+  pub feed_title: String,
+  // The language pages are served in unless a visitor asks for another, which
+  // must have a catalog under `paths.locales`.
+  pub language: String,
+  // Links to profiles elsewhere, by name, such as `github`.
+  #[serde(default)]
+  pub social: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Paths {
+  pub snippets: String,
+  pub writing: String,
+  // Where the data scripts for the front end are generated. Must be one of
+  // the asset roots so that it's served.
+  pub scripts: String,
+  // Where the stylesheet compiled from the themes is written, which must be
+  // an asset root too.
+  pub styles: String,
+  pub templates: String,
+  // Message catalogs for the site's own text, one `<language>.toml` each.
+  pub locales: String,
+  pub export: String,
+  // Resume data in the JSON Resume schema.
+  pub resume: String,
+}
+
+// Geometry of the animated code and writing windows on the index page, in
+// the units of their 100x100 SVG view boxes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Animation {
+  pub window_lines: u32,
+  pub code_origin: [f64; 2],
+  pub writing_origin: [f64; 2],
+  pub line_delta: [f64; 2],
+  pub step_millis: u32,
+}
+
+// The themes served to visitors who haven't chosen one, by whether their
+// system prefers a light or a dark colour scheme.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeChoice {
+  pub light: String,
+  pub dark: String,
+}
+
+// A font family served from the `fonts` asset root, by its files.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Font {
+  pub regular: String,
+  pub bold: Option<String>,
+}
+
+// The colours of a theme, as hex, and its fonts, by the families in
+// `fonts`. The page fonts fall back to the system's own sans-serif and
+// monospace fonts.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+  pub background: String,
+  // Behind the writing on the index page.
+  pub surface: String,
+  pub text: String,
+  pub muted: String,
+  pub accent: String,
+  pub code_background: String,
+  pub code_text: String,
+  pub body_font: String,
+  pub mono_font: String,
+  // For the name on the index page and the status on error pages.
+  pub display_font: String,
+  // How fast the index page scrolls, as a multiple of `animation.step_millis`.
+  pub animation_speed: f64,
+}
+
+// Paths crawlers are asked to stay out of. They're left out of the sitemap
+// too.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Robots {
+  #[serde(default)]
+  pub disallow: Vec<String>,
+}
+
+impl SiteConfig {
+  // Load the file named by `HOMUPAGE_CONFIG`, or `site.toml`, applying
+  // overrides from the environment.
+  pub fn load() -> Result<SiteConfig, io::Error> {
+    let file = env::var(CONFIG_FILE_VAR).unwrap_or_else(|_| DEFAULT_FILE.to_string());
+    let content = fs::read_to_string(&file)
+      .map_err(|err| io::Error::new(err.kind(), format!("Could not read {}: {}", file, err)))?;
+
+    SiteConfig::parse(&content, env::vars())
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", file, err)))
+  }
+
+  pub fn parse<I>(content: &str, vars: I) -> Result<SiteConfig, String>
+    where I: IntoIterator<Item = (String, String)>
+  {
+    let mut value = content.parse::<Value>().map_err(|err| err.to_string())?;
+
+    for (name, setting) in vars {
+      if name == CONFIG_FILE_VAR || !name.starts_with(ENV_PREFIX) {
+        continue;
+      }
+      let key = name[ENV_PREFIX.len()..].to_lowercase();
+      if !apply_override(&mut value, &key, &setting)? {
+        return Err(format!("{} doesn't name a setting", name));
+      }
+    }
+
+    let mut config = value.try_into::<SiteConfig>().map_err(|err| err.to_string())?;
+    config.site.url = config.site.url.trim_end_matches('/').to_string();
+    config.validate()?;
+
+    Ok(config)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    for (name, value) in &[
+      ("site.title", &self.site.title),
+      ("site.author", &self.site.author),
+      ("site.feed_title", &self.site.feed_title),
+    ] {
+      if value.trim().is_empty() {
+        problems.push(format!("{} can't be empty", name));
+      }
+    }
+    if !is_url(&self.site.url) {
+      problems.push(format!("site.url must be an http(s) URL, found `{}`", self.site.url));
+    }
+    for (name, url) in &self.site.social {
+      if !is_url(url) {
+        problems.push(format!("site.social.{} must be an http(s) URL, found `{}`", name, url));
+      }
+    }
+
+    for (name, dir) in &[
+      ("paths.snippets", &self.paths.snippets),
+      ("paths.writing", &self.paths.writing),
+      ("paths.scripts", &self.paths.scripts),
+      ("paths.styles", &self.paths.styles),
+      ("paths.templates", &self.paths.templates),
+      ("paths.locales", &self.paths.locales),
+    ] {
+      if !Path::new(dir).is_dir() {
+        problems.push(format!("{} must be a directory, found `{}`", name, dir));
+      }
+    }
+    if !Path::new(&self.paths.locales).join(format!("{}.toml", self.site.language)).is_file() {
+      problems.push(format!("site.language must have a catalog in paths.locales, found `{}`", self.site.language));
+    }
+    if !Path::new(&self.paths.resume).is_file() {
+      problems.push(format!("paths.resume must be a file, found `{}`", self.paths.resume));
+    }
+    for (name, dir) in &[("paths.scripts", &self.paths.scripts), ("paths.styles", &self.paths.styles)] {
+      let root = dir.trim_start_matches("./").trim_end_matches('/');
+      if !assets::ROOTS.contains(&root) {
+        problems.push(format!("{} must be one of the asset roots {:?}", name, assets::ROOTS));
+      }
+    }
+
+    for (setting, name) in &[("theme.light", &self.theme.light), ("theme.dark", &self.theme.dark)] {
+      if !self.themes.contains_key(name.as_str()) {
+        problems.push(format!("{} must name one of the themes, found `{}`", setting, name));
+      }
+    }
+    for (family, font) in &self.fonts {
+      for file in Some(&font.regular).into_iter().chain(font.bold.as_ref()) {
+        if !Path::new(assets::FONTS).join(file).is_file() {
+          problems.push(format!("fonts.\"{}\" must name files in {}/, found `{}`", family, assets::FONTS, file));
+        }
+      }
+    }
+    for (name, theme) in &self.themes {
+      if !is_theme_name(name) {
+        problems.push(format!("themes.{} must be named with lowercase letters, digits and dashes", name));
+      }
+      for (setting, colour) in &[
+        ("background", &theme.background),
+        ("surface", &theme.surface),
+        ("text", &theme.text),
+        ("muted", &theme.muted),
+        ("accent", &theme.accent),
+        ("code_background", &theme.code_background),
+        ("code_text", &theme.code_text),
+      ] {
+        if !is_hex_colour(colour) {
+          problems.push(format!("themes.{}.{} must be a hex colour such as #17546d, found `{}`", name, setting, colour));
+        }
+      }
+      for (setting, family) in &[
+        ("body_font", &theme.body_font),
+        ("mono_font", &theme.mono_font),
+        ("display_font", &theme.display_font),
+      ] {
+        if !self.fonts.contains_key(family.as_str()) {
+          problems.push(format!("themes.{}.{} must be one of the fonts, found `{}`", name, setting, family));
+        }
+      }
+      if !theme.animation_speed.is_finite() || theme.animation_speed <= 0.0 {
+        problems.push(format!("themes.{}.animation_speed must be more than 0", name));
+      }
+    }
+
+    for path in &self.robots.disallow {
+      if !path.starts_with('/') {
+        problems.push(format!("robots.disallow must list paths, found `{}`", path));
+      }
+    }
+
+    let animation = &self.animation;
+    if animation.window_lines == 0 || animation.window_lines > MAX_WINDOW_LINES {
+      problems.push(format!("animation.window_lines must be between 1 and {}", MAX_WINDOW_LINES));
+    }
+    if animation.step_millis == 0 {
+      problems.push("animation.step_millis must be more than 0".to_string());
+    }
+    let coordinates = animation.code_origin
+      .iter()
+      .chain(animation.writing_origin.iter())
+      .chain(animation.line_delta.iter());
+    for coordinate in coordinates {
+      if !coordinate.is_finite() || *coordinate < 0.0 || *coordinate > 100.0 {
+        problems.push(format!("animation coordinates must be between 0 and 100, found {}", coordinate));
+      }
+    }
+
+    if problems.is_empty() {
+      Ok(())
+    } else {
+      Err(problems.join("; "))
+    }
+  }
+}
+
+// Find the setting named by an underscore-separated key, where section and
+// setting names may contain underscores themselves, and set it from a string
+// parsed to the type of the setting it replaces.
+fn apply_override(value: &mut Value, key: &str, setting: &str) -> Result<bool, String> {
+  let table = match value.as_table_mut() {
+    Some(table) => table,
+    None        => return Ok(false),
+  };
+
+  let names = table.keys().cloned().collect::<Vec<String>>();
+  for name in names {
+    let lower = name.to_lowercase();
+
+    if key == lower {
+      let current = table.get_mut(&name).unwrap();
+      *current = typed(current, setting).map_err(|err| format!("{}: {}", name, err))?;
+      return Ok(true);
+    }
+    let nested = key.starts_with(&lower) && key[lower.len()..].starts_with('_');
+    if nested && apply_override(table.get_mut(&name).unwrap(), &key[lower.len() + 1..], setting)? {
+      return Ok(true);
+    }
+  }
+
+  Ok(false)
+}
+
+fn typed(current: &Value, setting: &str) -> Result<Value, String> {
+  let invalid = |kind: &str| format!("expected {}, found `{}`", kind, setting);
+
+  match current {
+    Value::Integer(_) => setting.parse().map(Value::Integer).map_err(|_| invalid("an integer")),
+    Value::Float(_)   => setting.parse().map(Value::Float).map_err(|_| invalid("a number")),
+    Value::Boolean(_) => setting.parse().map(Value::Boolean).map_err(|_| invalid("true or false")),
+    Value::Array(_)   => format!("value = {}", setting)
+      .parse::<Value>()
+      .ok()
+      .and_then(|parsed| parsed.get("value").cloned())
+      .ok_or_else(|| invalid("an array such as [1.0, 2.0]")),
+    _                 => Ok(Value::String(setting.to_string())),
+  }
+}
+
+// Theme names go into cookies, URLs and attribute selectors as they are.
+pub fn is_theme_name(name: &str) -> bool {
+  !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn is_hex_colour(colour: &str) -> bool {
+  colour.starts_with('#')
+    && [4, 5, 7, 9].contains(&colour.len())
+    && colour[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_url(url: &str) -> bool {
+  (url.starts_with("https://") || url.starts_with("http://")) && !url.contains(char::is_whitespace)
+}
@@ -0,0 +1,156 @@
+use rocket::State;
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+use serde_json::Value;
+
+use config::Site;
+use resume::Resume;
+
+
+// Content lines longer than this many bytes are folded, as RFC 6350 asks.
+const VCARD_LINE_LENGTH: usize = 75;
+
+#[get("/contact.vcf")]
+pub fn vcard(contact: State<Contact>) -> Content<String> {
+  Content(ContentType::new("text", "vcard"), contact.to_vcard())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Profile {
+  pub network: String,
+  pub url: String,
+}
+
+// Who the site belongs to, from the resume and the site configuration, for
+// contact apps and indexers.
+#[derive(Clone, Debug, Serialize)]
+pub struct Contact {
+  pub name: String,
+  pub given_name: String,
+  pub family_name: String,
+  pub title: Option<String>,
+  pub email: Option<String>,
+  pub url: String,
+  pub note: Option<String>,
+  pub profiles: Vec<Profile>,
+}
+
+impl Contact {
+  pub fn new(site: &Site, resume: &Resume) -> Contact {
+    let basics = &resume.basics;
+
+    // Profiles in the resume come first; social links from site.toml fill in
+    // any it doesn't have.
+    let mut profiles = basics.profiles
+      .iter()
+      .filter_map(|profile| profile.url.as_ref().map(|url| Profile {
+        network: profile.network.clone(),
+        url: url.clone(),
+      }))
+      .collect::<Vec<Profile>>();
+    for (network, url) in &site.social {
+      if !profiles.iter().any(|profile| profile.url.trim_end_matches('/') == url.trim_end_matches('/')) {
+        profiles.push(Profile { network: network.clone(), url: url.clone() });
+      }
+    }
+
+    // The last word of a name is taken to be the family name.
+    let mut words = basics.name.split_whitespace().collect::<Vec<&str>>();
+    let family_name = if words.len() > 1 { words.pop().unwrap_or("") } else { "" };
+
+    Contact {
+      name: basics.name.clone(),
+      given_name: words.join(" "),
+      family_name: family_name.to_string(),
+      title: basics.label.clone(),
+      email: basics.email.clone(),
+      url: site.url.clone(),
+      note: basics.summary.clone(),
+      profiles,
+    }
+  }
+
+  // A vCard 4.0, https://tools.ietf.org/html/rfc6350.
+  pub fn to_vcard(&self) -> String {
+    let mut lines = vec![
+      "BEGIN:VCARD".to_string(),
+      "VERSION:4.0".to_string(),
+      "KIND:individual".to_string(),
+      format!("FN:{}", escape_vcard(&self.name)),
+      format!("N:{};{};;;", escape_vcard(&self.family_name), escape_vcard(&self.given_name)),
+    ];
+    if let Some(ref title) = self.title {
+      lines.push(format!("TITLE:{}", escape_vcard(title)));
+    }
+    if let Some(ref email) = self.email {
+      lines.push(format!("EMAIL;TYPE=work:{}", escape_vcard(email)));
+    }
+    lines.push(format!("URL:{}", self.url));
+    for profile in &self.profiles {
+      lines.push(format!("X-SOCIALPROFILE;TYPE={}:{}", profile.network.to_lowercase(), profile.url));
+    }
+    if let Some(ref note) = self.note {
+      lines.push(format!("NOTE:{}", escape_vcard(note)));
+    }
+    lines.push(format!("SOURCE:{}/contact.vcf", self.url));
+    lines.push("END:VCARD".to_string());
+
+    lines
+      .iter()
+      .map(|line| fold(line))
+      .collect::<Vec<String>>()
+      .concat()
+  }
+
+  // A schema.org `Person`, for `<script type="application/ld+json">`.
+  pub fn to_json_ld(&self) -> Value {
+    let mut person = json!({
+      "@context": "https://schema.org",
+      "@type": "Person",
+      "name": self.name,
+      "givenName": self.given_name,
+      "familyName": self.family_name,
+      "url": self.url,
+      "sameAs": self.profiles.iter().map(|profile| &profile.url).collect::<Vec<&String>>(),
+    });
+    if let Some(ref title) = self.title {
+      person["jobTitle"] = json!(title);
+    }
+    if let Some(ref email) = self.email {
+      person["email"] = json!(format!("mailto:{}", email));
+    }
+    if let Some(ref note) = self.note {
+      person["description"] = json!(note);
+    }
+
+    person
+  }
+}
+
+fn escape_vcard(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(',', "\\,")
+    .replace(';', "\\;")
+    .replace("\r\n", "\\n")
+    .replace('\n', "\\n")
+}
+
+// Break a content line into pieces of at most 75 bytes, each continued on
+// the next line after a space, without splitting a character.
+fn fold(line: &str) -> String {
+  let mut folded = String::with_capacity(line.len() + 2);
+  let mut length = 0;
+
+  for c in line.chars() {
+    if length + c.len_utf8() > VCARD_LINE_LENGTH {
+      folded.push_str("\r\n ");
+      length = 1;
+    }
+    folded.push(c);
+    length += c.len_utf8();
+  }
+  folded.push_str("\r\n");
+
+  folded
+}
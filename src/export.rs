@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rocket::Rocket;
+use serde_json;
+
+use assets;
+use code::SourceCodeDirectory;
+use compression;
+use config::SiteConfig;
+use contact::Contact;
+use feed;
+use fingerprint::{self, Manifest};
+use i18n::Catalogs;
+use motion::Motion;
+use pages;
+use preview::{self, Previews};
+use resume::{self, Resume};
+use segments::Frame;
+use sitemap::Sitemap;
+use templates::Templates;
+use writing::Sample;
+
+
+// Write the data animated by `js/main.js`, and the settings it's animated
+// with, into the scripts directory. Writing samples get a script for each
+// language, with translations in place of the samples they translate.
+pub fn write_data_scripts(
+  config: &SiteConfig,
+  catalogs: &Catalogs,
+  samples: &[Sample],
+  source_dirs: &[SourceCodeDirectory],
+) -> Result<(), io::Error> {
+  let js_dir = Path::new(&config.paths.scripts);
+  let code_snippets = serde_json::to_string(source_dirs)?;
+  let animation = &config.animation;
+  let site_config = json!({
+    "title": config.site.title,
+    "animation": {
+      "windowLines": animation.window_lines,
+      "codeOrigin": animation.code_origin,
+      "writingOrigin": animation.writing_origin,
+      "lineDelta": animation.line_delta,
+      "stepMillis": animation.step_millis,
+    },
+  });
+
+  for language in catalogs.languages() {
+    let localized = samples
+      .iter()
+      .map(|sample| sample.in_language(language))
+      .collect::<Vec<&Sample>>();
+    let writing_samples = serde_json::to_string(&localized)?;
+    fs::write(
+      js_dir.join(writing_samples_script(catalogs, language)),
+      format!("const WRITING_SAMPLES = {}", writing_samples))?;
+  }
+  fs::write(js_dir.join("code_snippets.js"), format!("const CODE_SNIPPETS = {}", code_snippets))?;
+  fs::write(js_dir.join("site_config.js"), format!("const SITE_CONFIG = {}", site_config))
+}
+
+// The default language keeps the script's original name.
+pub fn writing_samples_script(catalogs: &Catalogs, language: &str) -> String {
+  if language == catalogs.default_language() {
+    "writing_samples.js".to_string()
+  } else {
+    format!("writing_samples.{}.js", language)
+  }
+}
+
+// Render the whole site into a directory that can be served by any static
+// file server. Everything is rendered from what the server manages, so the
+// export matches what it would serve to a visitor who hasn't chosen a
+// language, since a static server can't negotiate one.
+pub fn export<P: AsRef<Path>>(out_dir: P, server: &Rocket) -> Result<(), io::Error> {
+  let out_dir = out_dir.as_ref();
+  let config = managed::<SiteConfig>(server)?;
+  let templates = managed::<Templates>(server)?;
+  let resume = managed::<Resume>(server)?;
+  let resume_pdf = managed::<resume::Pdf>(server)?;
+  let contact = managed::<Contact>(server)?;
+  let samples = managed::<Vec<Sample>>(server)?;
+  let source_dirs = managed::<Vec<SourceCodeDirectory>>(server)?;
+  let manifest = managed::<Manifest>(server)?;
+  let sitemap = managed::<Sitemap>(server)?;
+  let previews = managed::<Previews>(server)?;
+  let page = |html: &str| manifest.rewrite_html(html);
+  let locale = templates.catalogs().default_language();
+  let frame = Frame::first(&config.animation, samples, source_dirs, locale);
+
+  // Assets keep their logical names too, for anything linking to them
+  // directly.
+  for dir in assets::ROOTS {
+    copy_dir(dir, out_dir.join(dir))?;
+  }
+  for (logical, fingerprinted) in manifest.assets() {
+    fs::copy(logical, out_dir.join(fingerprinted))?;
+
+    for extension in &["br", "gz"] {
+      let sibling = compression::sibling_path(Path::new(logical), extension);
+      if sibling.is_file() {
+        fs::copy(sibling, compression::sibling_path(&out_dir.join(fingerprinted), extension))?;
+      }
+    }
+  }
+  manifest.write(out_dir.join(fingerprint::MANIFEST_FILE))?;
+
+  write(out_dir.join("index.html"), &page(&pages::index_page(templates, contact, &frame, Motion::Default, locale)?))?;
+  write(out_dir.join("contact.vcf"), &contact.to_vcard())?;
+  write(out_dir.join("resume/index.html"), &page(&pages::resume_page(templates, resume, locale)?))?;
+  write(out_dir.join("resume.txt"), &resume.to_text())?;
+  write(out_dir.join("resume.md"), &resume.to_markdown())?;
+  write(out_dir.join("resume.json"), resume.to_json())?;
+  fs::write(out_dir.join("resume.pdf"), &resume_pdf.0)?;
+
+  write(out_dir.join("feed.atom"), &feed::atom(&config.site, samples))?;
+  write(out_dir.join("feed.rss"), &feed::rss(&config.site, samples))?;
+
+  write(out_dir.join("writing/index.html"), &page(&pages::writing::index_page(templates, samples, locale)?))?;
+  for sample in samples {
+    if let Some(article) = pages::writing::article_page(templates, samples, &sample.slug, locale)? {
+      write(out_dir.join("writing").join(&sample.slug).join("index.html"), &page(&article))?;
+    }
+  }
+
+  let transcript = pages::transcript::transcript_page(templates, config, samples, source_dirs, &frame, &Default::default(), locale)?;
+  if let Some(transcript) = transcript {
+    write(out_dir.join("transcript/index.html"), &page(&transcript))?;
+  }
+
+  write(out_dir.join("code/index.html"), &page(&pages::code::index_page(templates, source_dirs, locale)?))?;
+  for dir in source_dirs {
+    for source in &dir.source_files {
+      if let Some(source_page) = pages::code::source_page(templates, source_dirs, &dir.language_name, &source.file_name, locale)? {
+        let path = out_dir
+          .join("code")
+          .join(&dir.language_name)
+          .join(&source.file_name)
+          .join("index.html");
+        write(path, &page(&source_page))?;
+      }
+    }
+  }
+
+  for path in preview::pages(samples, source_dirs) {
+    if let Some(png) = previews.get(&path, config, samples, source_dirs)? {
+      let file = out_dir.join(preview::image_path(&path).trim_start_matches('/'));
+      fs::create_dir_all(file.parent().unwrap())?;
+      fs::write(file, png)?;
+    }
+  }
+
+  write(out_dir.join("sitemap.xml"), &sitemap.to_xml())?;
+  write(out_dir.join("robots.txt"), &sitemap.robots_txt())?;
+
+  Ok(())
+}
+
+fn managed<T: Send + Sync + 'static>(server: &Rocket) -> Result<&T, io::Error> {
+  server
+    .state::<T>()
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "The server is missing state the export needs"))
+}
+
+fn write<P: AsRef<Path>>(path: P, content: &str) -> Result<(), io::Error> {
+  if let Some(parent) = path.as_ref().parent() {
+    fs::create_dir_all(parent)?;
+  }
+  fs::write(path, content)
+}
+
+fn copy_dir<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), io::Error> {
+  fs::create_dir_all(to.as_ref())?;
+
+  for entry in fs::read_dir(from)? {
+    let path = entry?.path();
+    if path.is_file() {
+      fs::copy(&path, to.as_ref().join(path.file_name().unwrap()))?;
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use rocket::State;
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+
+use config::{Site, SiteConfig};
+use pages::escape;
+use writing::Sample;
+
+
+#[get("/feed.atom")]
+pub fn atom_feed(config: State<SiteConfig>, samples: State<Vec<Sample>>) -> Content<String> {
+  Content(ContentType::new("application", "atom+xml"), atom(&config.site, &samples))
+}
+
+#[get("/feed.rss")]
+pub fn rss_feed(config: State<SiteConfig>, samples: State<Vec<Sample>>) -> Content<String> {
+  Content(ContentType::new("application", "rss+xml"), rss(&config.site, &samples))
+}
+
+pub fn atom(site: &Site, samples: &[Sample]) -> String {
+  let entries = newest_first(samples);
+  let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{title}</title>
+  <subtitle>{description}</subtitle>
+  <link href="{site}/writing"/>
+  <link rel="self" type="application/atom+xml" href="{site}/feed.atom"/>
+  <id>{site}/writing</id>
+  <updated>{updated}</updated>
+  <author>
+    <name>{author}</name>
+  </author>
+"#,
+    title = escape(&site.feed_title),
+    description = escape(&site.description),
+    site = site.url,
+    updated = last_updated(&entries).to_rfc3339_opts(SecondsFormat::Secs, true),
+    author = escape(&site.author));
+
+  for sample in entries {
+    feed.push_str(&format!(r#"  <entry>
+    <title>{title}</title>
+    <link href="{url}"/>
+    <id>{url}</id>
+    <updated>{updated}</updated>
+    <summary>{summary}</summary>
+  </entry>
+"#,
+      title = escape(&sample.title),
+      url = url(site, sample),
+      updated = sample.updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+      summary = escape(&sample.excerpt())));
+  }
+  feed.push_str("</feed>\n");
+
+  feed
+}
+
+pub fn rss(site: &Site, samples: &[Sample]) -> String {
+  let entries = newest_first(samples);
+  let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{title}</title>
+    <link>{site}/writing</link>
+    <description>{description}</description>
+    <lastBuildDate>{updated}</lastBuildDate>
+    <atom:link href="{site}/feed.rss" rel="self" type="application/rss+xml"/>
+"#,
+    title = escape(&site.feed_title),
+    site = site.url,
+    description = escape(&site.description),
+    updated = last_updated(&entries).to_rfc2822());
+
+  for sample in entries {
+    feed.push_str(&format!(r#"    <item>
+      <title>{title}</title>
+      <link>{url}</link>
+      <guid isPermaLink="true">{url}</guid>
+      <pubDate>{updated}</pubDate>
+      <description>{summary}</description>
+    </item>
+"#,
+      title = escape(&sample.title),
+      url = url(site, sample),
+      updated = sample.updated.to_rfc2822(),
+      summary = escape(&sample.excerpt())));
+  }
+  feed.push_str("  </channel>\n</rss>\n");
+
+  feed
+}
+
+fn newest_first(samples: &[Sample]) -> Vec<&Sample> {
+  let mut entries = samples.iter().collect::<Vec<&Sample>>();
+  entries.sort_by(|a, b| b.updated.cmp(&a.updated).then(a.slug.cmp(&b.slug)));
+  entries
+}
+
+fn last_updated(entries: &[&Sample]) -> DateTime<Utc> {
+  entries
+    .first()
+    .map(|sample| sample.updated)
+    .unwrap_or_else(Utc::now)
+}
+
+fn url(site: &Site, sample: &Sample) -> String {
+  format!("{}/writing/{}", site.url, sample.slug)
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json;
+
+use caching::content_hash;
+use compression;
+
+
+pub const MANIFEST_FILE: &str = "asset-manifest.json";
+
+const HASH_LENGTH: usize = 16;
+const URL_ATTRIBUTES: &[&str] = &["href=\"", "src=\""];
+
+// Maps logical asset names like `css/main.css` to fingerprinted names like
+// `css/main.3f2a9c1b04e5d6f7.css` that change whenever the content does.
+#[derive(Debug, Default, Serialize)]
+pub struct Manifest {
+  assets: BTreeMap<String, String>,
+  #[serde(skip)]
+  logical_names: BTreeMap<String, String>,
+}
+
+impl Manifest {
+  pub fn build(roots: &[&str]) -> Result<Manifest, io::Error> {
+    let mut manifest = Manifest::default();
+
+    for root in roots {
+      let mut files = Vec::new();
+      walk(Path::new(root), &mut files)?;
+
+      for file in files {
+        let logical = file.replace('\\', "/");
+        let hash = content_hash(&fs::read(&file)?);
+        let fingerprinted = fingerprinted_name(&logical, &hash[..HASH_LENGTH]);
+
+        manifest.logical_names.insert(fingerprinted.clone(), logical.clone());
+        manifest.assets.insert(logical, fingerprinted);
+      }
+    }
+
+    Ok(manifest)
+  }
+
+  pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+    fs::write(path, serde_json::to_string_pretty(self)?)
+  }
+
+  pub fn assets(&self) -> &BTreeMap<String, String> {
+    &self.assets
+  }
+
+  pub fn fingerprinted(&self, logical: &str) -> Option<&str> {
+    self.assets.get(logical).map(String::as_str)
+  }
+
+  pub fn logical(&self, fingerprinted: &str) -> Option<&str> {
+    self.logical_names.get(fingerprinted).map(String::as_str)
+  }
+
+  // Point every `href` and `src` attribute that names a known asset at its
+  // fingerprinted name, keeping relative references relative.
+  pub fn rewrite_html(&self, html: &str) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((start, attribute)) = next_attribute(rest) {
+      let value_start = start + attribute.len();
+      let value_end = match rest[value_start..].find('"') {
+        Some(length) => value_start + length,
+        None         => break,
+      };
+      let value = &rest[value_start..value_end];
+      let leading_slash = if value.starts_with('/') { "/" } else { "" };
+
+      rewritten.push_str(&rest[..value_start]);
+      match self.fingerprinted(value.trim_start_matches('/')) {
+        Some(fingerprinted) => {
+          rewritten.push_str(leading_slash);
+          rewritten.push_str(fingerprinted);
+        },
+        None                => rewritten.push_str(value),
+      }
+      rest = &rest[value_end..];
+    }
+    rewritten.push_str(rest);
+
+    rewritten
+  }
+}
+
+fn next_attribute(html: &str) -> Option<(usize, &'static str)> {
+  URL_ATTRIBUTES
+    .iter()
+    .filter_map(|attribute| html.find(attribute).map(|start| (start, *attribute)))
+    .min_by_key(|&(start, _)| start)
+}
+
+// `main.css` becomes `main.<hash>.css`.
+fn fingerprinted_name(logical: &str, hash: &str) -> String {
+  let file_start = logical.rfind('/').map(|slash| slash + 1).unwrap_or(0);
+
+  match logical[file_start..].rfind('.') {
+    Some(dot) if dot > 0 => {
+      let dot = file_start + dot;
+      format!("{}.{}{}", &logical[..dot], hash, &logical[dot..])
+    },
+    _                    => format!("{}.{}", logical, hash),
+  }
+}
+
+fn walk(dir: &Path, files: &mut Vec<String>) -> Result<(), io::Error> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    let is_hidden = path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .map(|name| name.starts_with('.'))
+      .unwrap_or(true);
+
+    if is_hidden || compression::is_precompressed(&path) {
+      continue;
+    } else if path.is_dir() {
+      walk(&path, files)?;
+    } else if let Some(path) = path.to_str() {
+      files.push(path.to_string());
+    }
+  }
+
+  Ok(())
+}
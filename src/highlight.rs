@@ -0,0 +1,251 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Class {
+  Plain,
+  Keyword,
+  Type,
+  Str,
+  Number,
+  Comment,
+}
+
+impl Class {
+  pub fn css_class(&self) -> Option<&'static str> {
+    match *self {
+      Class::Plain   => None,
+      Class::Keyword => Some("kw"),
+      Class::Type    => Some("ty"),
+      Class::Str     => Some("st"),
+      Class::Number  => Some("nu"),
+      Class::Comment => Some("co"),
+    }
+  }
+}
+
+struct Syntax {
+  title: &'static str,
+  keywords: &'static [&'static str],
+  line_comment: &'static str,
+  block_comment: Option<(&'static str, &'static str)>,
+  string_quotes: &'static [char],
+  // Languages like Rust and Elm use single quotes for characters and
+  // lifetimes, so only short quoted runs are treated as literals.
+  char_quote: Option<char>,
+}
+
+const RUST: Syntax = Syntax {
+  title: "Rust",
+  keywords: &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+  ],
+  line_comment: "//",
+  block_comment: Some(("/*", "*/")),
+  string_quotes: &['"'],
+  char_quote: Some('\''),
+};
+
+const GO: Syntax = Syntax {
+  title: "Go",
+  keywords: &[
+    "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough",
+    "false", "for", "func", "go", "goto", "if", "import", "interface", "map", "nil",
+    "package", "range", "return", "select", "struct", "switch", "true", "type", "var",
+  ],
+  line_comment: "//",
+  block_comment: Some(("/*", "*/")),
+  string_quotes: &['"', '`'],
+  char_quote: Some('\''),
+};
+
+const PYTHON: Syntax = Syntax {
+  title: "Python",
+  keywords: &[
+    "False", "None", "True", "and", "as", "assert", "break", "class", "continue", "def",
+    "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+    "while", "with", "yield",
+  ],
+  line_comment: "#",
+  block_comment: None,
+  string_quotes: &['"', '\''],
+  char_quote: None,
+};
+
+const ELM: Syntax = Syntax {
+  title: "Elm",
+  keywords: &[
+    "alias", "as", "case", "else", "exposing", "if", "import", "in", "let", "module", "of",
+    "port", "then", "type",
+  ],
+  line_comment: "--",
+  block_comment: Some(("{-", "-}")),
+  string_quotes: &['"'],
+  char_quote: Some('\''),
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+  title: "JavaScript",
+  keywords: &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+    "delete", "do", "else", "export", "extends", "false", "finally", "for", "function", "if",
+    "import", "in", "instanceof", "let", "new", "null", "of", "return", "switch", "this",
+    "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "yield",
+  ],
+  line_comment: "//",
+  block_comment: Some(("/*", "*/")),
+  string_quotes: &['"', '\'', '`'],
+  char_quote: None,
+};
+
+const PLAIN: Syntax = Syntax {
+  title: "",
+  keywords: &[],
+  line_comment: "",
+  block_comment: None,
+  string_quotes: &[],
+  char_quote: None,
+};
+
+fn syntax(language: &str) -> &'static Syntax {
+  match language {
+    "rust"       => &RUST,
+    "go"         => &GO,
+    "python"     => &PYTHON,
+    "elm"        => &ELM,
+    "javascript" => &JAVASCRIPT,
+    _            => &PLAIN,
+  }
+}
+
+pub fn language_title(language: &str) -> String {
+  match syntax(language).title {
+    ""    => language.to_string(),
+    title => title.to_string(),
+  }
+}
+
+// Split a single line of source code into classified spans. Each line is
+// highlighted on its own, so constructs spanning lines are not tracked.
+pub fn highlight<'a>(language: &str, line: &'a str) -> Vec<(Class, &'a str)> {
+  let syntax = syntax(language);
+  let mut spans: Vec<(Class, usize, usize)> = Vec::new();
+  let mut offset = 0;
+
+  while let Some(c) = line[offset..].chars().next() {
+    let rest = &line[offset..];
+    let (class, length) = if !syntax.line_comment.is_empty() && rest.starts_with(syntax.line_comment) {
+      (Class::Comment, rest.len())
+    } else if let Some((open, close)) = syntax.block_comment.filter(|&(open, _)| rest.starts_with(open)) {
+      let length = rest[open.len()..]
+        .find(close)
+        .map(|end| open.len() + end + close.len())
+        .unwrap_or(rest.len());
+      (Class::Comment, length)
+    } else if syntax.string_quotes.contains(&c) {
+      (Class::Str, quoted(rest, c).unwrap_or(rest.len()))
+    } else if syntax.char_quote == Some(c) {
+      match quoted(rest, c).filter(|&length| rest[..length].chars().count() <= 4) {
+        Some(length) => (Class::Str, length),
+        None         => (Class::Plain, c.len_utf8()),
+      }
+    } else if c.is_ascii_digit() {
+      (Class::Number, run(rest, |c| c.is_alphanumeric() || c == '.' || c == '_'))
+    } else if c.is_alphabetic() || c == '_' {
+      let length = run(rest, |c| c.is_alphanumeric() || c == '_');
+      if syntax.keywords.contains(&&rest[..length]) {
+        (Class::Keyword, length)
+      } else if c.is_uppercase() {
+        (Class::Type, length)
+      } else {
+        (Class::Plain, length)
+      }
+    } else {
+      (Class::Plain, c.len_utf8())
+    };
+
+    let end = offset + length;
+    match spans.last_mut() {
+      Some(last) if last.0 == Class::Plain && class == Class::Plain => last.2 = end,
+      _                                                             => spans.push((class, offset, end)),
+    }
+    offset = end;
+  }
+
+  spans
+    .into_iter()
+    .map(|(class, start, end)| (class, &line[start..end]))
+    .collect()
+}
+
+// The length of a quoted literal at the start of `text`, including both
+// quotes, or None if it is not terminated on this line.
+fn quoted(text: &str, quote: char) -> Option<usize> {
+  let mut escaped = false;
+
+  for (offset, c) in text.char_indices().skip(1) {
+    if escaped {
+      escaped = false;
+    } else if c == '\\' {
+      escaped = true;
+    } else if c == quote {
+      return Some(offset + c.len_utf8());
+    }
+  }
+
+  None
+}
+
+fn run<F: Fn(char) -> bool>(text: &str, predicate: F) -> usize {
+  text
+    .char_indices()
+    .find(|&(_, c)| !predicate(c))
+    .map(|(offset, _)| offset)
+    .unwrap_or(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::Class::*;
+
+  #[test]
+  fn comments_run_to_the_end_of_the_line_or_block() {
+    assert_eq!(highlight("rust", "let x = 1; // one"), vec![
+      (Keyword, "let"), (Plain, " x = "), (Number, "1"), (Plain, "; "), (Comment, "// one"),
+    ]);
+    assert_eq!(highlight("elm", "{- a -} b"), vec![(Comment, "{- a -}"), (Plain, " b")]);
+    assert_eq!(highlight("go", "/* open"), vec![(Comment, "/* open")]);
+    assert_eq!(highlight("python", "x # y"), vec![(Plain, "x "), (Comment, "# y")]);
+  }
+
+  #[test]
+  fn strings_end_at_an_unescaped_quote() {
+    assert_eq!(highlight("rust", r#"f("a \" b", c)"#), vec![
+      (Plain, "f("), (Str, r#""a \" b""#), (Plain, ", c)"),
+    ]);
+    assert_eq!(highlight("python", "'x' # not '"), vec![(Str, "'x'"), (Plain, " "), (Comment, "# not '")]);
+    assert_eq!(highlight("go", "`raw"), vec![(Str, "`raw")]);
+  }
+
+  #[test]
+  fn char_literals_are_told_apart_from_lifetimes() {
+    assert_eq!(highlight("rust", "'a'"), vec![(Str, "'a'")]);
+    assert_eq!(highlight("rust", r"'\n'"), vec![(Str, r"'\n'")]);
+    assert_eq!(highlight("rust", "fn f<'a>(x: &'a str)"), vec![
+      (Keyword, "fn"), (Plain, " f<'a>(x: &'a str)"),
+    ]);
+    assert_eq!(highlight("rust", "&'static str"), vec![(Plain, "&'"), (Keyword, "static"), (Plain, " str")]);
+  }
+
+  #[test]
+  fn keywords_and_types_depend_on_the_language() {
+    assert_eq!(highlight("rust", "pub struct Line"), vec![
+      (Keyword, "pub"), (Plain, " "), (Keyword, "struct"), (Plain, " "), (Type, "Line"),
+    ]);
+    assert_eq!(highlight("python", "None"), vec![(Keyword, "None")]);
+    assert_eq!(highlight("go", "let"), vec![(Plain, "let")]);
+    assert_eq!(highlight("cobol", "let \"x\""), vec![(Plain, "let \"x\"")]);
+  }
+}
@@ -0,0 +1,204 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+use rocket::{Outcome, Request, State};
+use rocket::http::{Cookie, SameSite};
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder};
+use serde_json::{self, Value};
+use toml;
+
+use compression;
+use templates::Templates;
+
+
+const COOKIE: &str = "lang";
+
+// The text of the site's own pages in each language, from a
+// `<language>.toml` under `paths.locales`. Anything a catalog leaves out is
+// taken from the default language's.
+pub struct Catalogs {
+  default: String,
+  messages: BTreeMap<String, Value>,
+}
+
+impl Catalogs {
+  pub fn load<P: AsRef<Path>>(dir: P, default: &str) -> Result<Catalogs, io::Error> {
+    let mut messages = BTreeMap::new();
+    for entry in fs::read_dir(dir.as_ref())? {
+      let path = entry?.path();
+      if path.extension().and_then(|extension| extension.to_str()) != Some("toml") {
+        continue;
+      }
+
+      let language = path.file_stem().unwrap().to_string_lossy().to_string();
+      let catalog = fs::read_to_string(&path)?
+        .parse::<toml::Value>()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", path.display(), err)))?;
+      messages.insert(language, serde_json::to_value(catalog)?);
+    }
+
+    let fallback = messages
+      .get(default)
+      .cloned()
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("There's no catalog for {} in {}", default, dir.as_ref().display())))?;
+    for catalog in messages.values_mut() {
+      merge(catalog, &fallback);
+    }
+
+    Ok(Catalogs {
+      default: default.to_string(),
+      messages,
+    })
+  }
+
+  pub fn default_language(&self) -> &str {
+    &self.default
+  }
+
+  pub fn languages(&self) -> Vec<&str> {
+    self.messages.keys().map(String::as_str).collect()
+  }
+
+  pub fn messages(&self, language: &str) -> &Value {
+    self.messages
+      .get(language)
+      .unwrap_or(&self.messages[&self.default])
+  }
+
+  // A message by its dotted key, like `nav.writing`, or the key itself if no
+  // catalog has it.
+  pub fn message(&self, language: &str, key: &str) -> String {
+    key
+      .split('.')
+      .try_fold(self.messages(language), |messages, name| messages.get(name))
+      .and_then(Value::as_str)
+      .unwrap_or(key)
+      .to_string()
+  }
+
+  // What to add to a page's URL to ask for it in `language`. The default
+  // language's pages are at their plain URLs.
+  pub fn query(&self, language: &str) -> String {
+    if language == self.default {
+      String::new()
+    } else {
+      format!("?lang={}", language)
+    }
+  }
+
+  // The first of our languages an `Accept-Language` header asks for, weighing
+  // them by quality and then by the order they're listed in.
+  pub fn negotiate(&self, accept_language: &str) -> Option<&str> {
+    let mut ranges = accept_language
+      .split(',')
+      .enumerate()
+      .filter_map(|(order, range)| {
+        let mut parameters = range.split(';');
+        let tag = parameters.next()?.trim();
+        let quality = parameters
+          .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+          .map(|quality| quality.parse::<f32>().unwrap_or(0.0))
+          .next()
+          .unwrap_or(1.0);
+        Some((tag, quality, order))
+      })
+      .filter(|(_, quality, _)| *quality > 0.0)
+      .collect::<Vec<(&str, f32, usize)>>();
+    ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.2.cmp(&b.2)));
+
+    ranges.iter().filter_map(|(tag, _, _)| self.supported(tag)).next()
+  }
+
+  // One of our languages by its tag, where a region falls back to the
+  // language alone, so `ja-JP` is served in `ja`.
+  pub fn supported(&self, tag: &str) -> Option<&str> {
+    let tag = tag.to_lowercase();
+    let primary = tag.split('-').next().unwrap_or("");
+
+    self.messages
+      .keys()
+      .find(|language| language.to_lowercase() == tag)
+      .or_else(|| self.messages.keys().find(|language| language.to_lowercase() == primary))
+      .map(String::as_str)
+  }
+}
+
+// Fill in whatever `catalog` is missing from `fallback`, table by table.
+fn merge(catalog: &mut Value, fallback: &Value) {
+  if let (Some(catalog), Some(fallback)) = (catalog.as_object_mut(), fallback.as_object()) {
+    for (name, message) in fallback {
+      match catalog.get_mut(name) {
+        Some(existing) => merge(existing, message),
+        None           => { catalog.insert(name.clone(), message.clone()); },
+      }
+    }
+  }
+}
+
+// The language a page is rendered in. `?lang=ja` chooses one, and is
+// remembered in a cookie; failing those, `Accept-Language` decides.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Locale(pub String);
+
+impl Deref for Locale {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Locale {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Locale, ()> {
+    let catalogs = request.guard::<State<Templates>>()?.inner().catalogs();
+
+    let chosen = request
+      .get_query_value::<String>("lang")
+      .and_then(Result::ok)
+      .and_then(|tag| catalogs.supported(&tag).map(str::to_string));
+    if let Some(language) = chosen {
+      let cookie = Cookie::build(COOKIE, language.clone())
+        .path("/")
+        .same_site(SameSite::Lax)
+        .permanent()
+        .finish();
+      request.cookies().add(cookie);
+      return Outcome::Success(Locale(language));
+    }
+
+    let remembered = request
+      .cookies()
+      .get(COOKIE)
+      .and_then(|cookie| catalogs.supported(cookie.value()).map(str::to_string));
+    let accepted = request
+      .headers()
+      .get_one("Accept-Language")
+      .and_then(|accept_language| catalogs.negotiate(accept_language).map(str::to_string));
+
+    let language = remembered
+      .or(accepted)
+      .unwrap_or_else(|| catalogs.default_language().to_string());
+    Outcome::Success(Locale(language))
+  }
+}
+
+// A page that differs by language, which caches have to key on.
+pub struct ByLocale<R>(pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for ByLocale<R> {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = self.0.respond_to(request)?;
+    compression::add_vary(&mut response, "Accept-Language");
+    compression::add_vary(&mut response, "Cookie");
+    Ok(response)
+  }
+}
@@ -1,104 +1,232 @@
-#![feature(custom_attribute, proc_macro_hygiene, decl_macro)]
+#![feature(proc_macro_hygiene, decl_macro)]
 
+extern crate ab_glyph;
+extern crate base64;
+extern crate brotli;
+extern crate chrono;
+extern crate flate2;
+extern crate png;
+extern crate printpdf;
 #[macro_use] extern crate rocket;
+extern crate rust_stemmers;
 extern crate serde;
 #[macro_use] extern crate serde_derive;
-extern crate serde_json;
+#[macro_use] extern crate serde_json;
+extern crate sha2;
+extern crate tera;
+extern crate toml;
+extern crate ttf_parser;
 
+mod api;
+mod assets;
+mod caching;
 mod code;
+mod compression;
+mod config;
+mod contact;
+mod export;
+mod feed;
+mod fingerprint;
+mod highlight;
+mod i18n;
+mod motion;
+mod pages;
+mod preview;
+mod raster;
+mod redirects;
+mod resume;
+mod scroll;
+mod search;
+mod security;
+mod segments;
+mod sitemap;
+mod templates;
+mod themes;
 mod writing;
 
-use std::fs;
-use std::io::Write;
-use std::path::Path;
+#[cfg(test)] mod tests;
+
+use std::env;
+use std::io;
+
+use code::SourceCodeDirectory;
+use config::SiteConfig;
+use contact::Contact;
+use fingerprint::Manifest;
+use i18n::{ByLocale, Catalogs, Locale};
+use motion::{ByMotion, Motion};
+use resume::Resume;
+use segments::Frame;
+use templates::Templates;
+use writing::Sample;
 
 use rocket::{
-  http::ContentType,
-  response::{
-    content::Html,
-    NamedFile,
-    Redirect,
-    Response,
-  },
+  Request,
+  Rocket,
+  State,
+  response::content::Html,
 };
 
 
-#[get("/css/<filename>")]
-fn css<'r>(filename: String) -> Response<'r> {
-  let file_path = Path::new("css/").join(filename);
-
-  if let Ok(file) = NamedFile::open(file_path) {
-    Response::build()
-      .header(ContentType::CSS)
-      .sized_body(file)
-      .finalize()
-  } else {
-    let err_msg = "No such file";
-    Response::build()
-      .header(ContentType::Plain)
-      .sized_body(::std::io::Cursor::new(err_msg))
-      .finalize()
-  }
+#[get("/")]
+fn index(
+  motion: Motion,
+  locale: Locale,
+  frame: Frame,
+  contact: State<Contact>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<ByLocale<ByMotion<Html<String>>>, io::Error> {
+  let page = pages::index_page(&templates, &contact, &frame, motion, &locale)?;
+  Ok(ByLocale(ByMotion(Html(manifest.rewrite_html(&page)))))
 }
 
-#[get("/js/<filename>")]
-fn js<'r>(filename: String) -> Response<'r> {
-  let file_path = Path::new("js/").join(filename);
-
-  if let Ok(file) = NamedFile::open(file_path) {
-    Response::build()
-      .header(ContentType::JavaScript)
-      .sized_body(file)
-      .finalize()
-  } else {
-    let err_msg = "No such file";
-    Response::build()
-      .header(ContentType::Plain)
-      .sized_body(::std::io::Cursor::new(err_msg))
-      .finalize()
-  }
+// Logged so that dead links, ours or anyone else's, can be tracked down.
+#[catch(404)]
+fn not_found(request: &Request) -> ByLocale<Html<String>> {
+  eprintln!(
+    "404 Not Found: {} (referer: {})",
+    request.uri(),
+    request.headers().get_one("Referer").unwrap_or("-"));
+  error_page(request, |templates, locale| pages::not_found(templates, request.uri().path(), locale))
 }
 
-#[get("/resume")]
-fn resume() -> Html<NamedFile> {
-  Html(NamedFile::open("html/resume.html").unwrap())
+#[catch(500)]
+fn server_error(request: &Request) -> ByLocale<Html<String>> {
+  eprintln!("500 Internal Server Error: {}", request.uri());
+  error_page(request, pages::server_error)
 }
 
-#[get("/")]
-fn index() -> Html<NamedFile> {
-  Html(NamedFile::open("html/index.html").unwrap())
-}
+// Error pages fall back to plain text, since a broken template may be what
+// went wrong in the first place.
+fn error_page<F>(request: &Request, render: F) -> ByLocale<Html<String>>
+  where F: Fn(&Templates, &str) -> Result<String, io::Error>
+{
+  let page = request
+    .guard::<State<Templates>>()
+    .succeeded()
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Templates are not loaded"))
+    .and_then(|templates| {
+      let locale = request
+        .guard::<Locale>()
+        .succeeded()
+        .unwrap_or_else(|| Locale(templates.catalogs().default_language().to_string()));
+      render(&templates, &locale)
+    });
 
-#[catch(404)]
-fn not_found() -> Redirect {
-  Redirect::to(uri!(index))
+  match (page, request.guard::<State<Manifest>>().succeeded()) {
+    (Ok(page), Some(manifest)) => ByLocale(Html(manifest.rewrite_html(&page))),
+    (Ok(page), None)           => ByLocale(Html(page)),
+    (Err(err), _)              => {
+      eprintln!("Could not render error page: {}", err);
+      ByLocale(Html("<!doctype html>\n<title>Error</title>\n<p>Something went wrong.</p>\n".to_string()))
+    },
+  }
 }
 
 fn main() {
-  let source_dirs = code::analyze("./snippets")
-    .map(|dirs| serde_json::to_vec(&dirs).unwrap())
+  let config = SiteConfig::load()
+    .unwrap_or_else(|err| panic!("{}", err));
+  let catalogs = Catalogs::load(&config.paths.locales, &config.site.language)
+    .unwrap_or_else(|err| panic!("Could not load the catalogs in {}: {}", config.paths.locales, err));
+  let templates = Templates::load(&config.paths.templates, &config, catalogs)
+    .expect("Could not load templates");
+  let resume = Resume::load(&config.paths.resume)
+    .unwrap_or_else(|err| panic!("Could not load {}: {}", config.paths.resume, err));
+  let source_dirs = code::analyze(&config.paths.snippets)
     .expect("Code analysis failed");
-  let mut code_snippets_js = fs::OpenOptions::new()
-    .write(true)
-    .create(true)
-    .open("./js/code_snippets.js")
-    .expect("Could not open js/code_snippets.js");
-  code_snippets_js.write(b"const CODE_SNIPPETS = ").unwrap();
-  code_snippets_js.write_all(&source_dirs).unwrap();
-
-  let writing_samples = writing::collect("./writing")
-    .map(|samples| serde_json::to_vec(&samples).unwrap())
+  let writing_samples = writing::collect(&config.paths.writing, templates.catalogs())
     .expect("Failed to load writing samples");
-  let mut writing_samples_js = fs::OpenOptions::new()
-    .write(true)
-    .create(true)
-    .open("./js/writing_samples.js")
-    .expect("Could not open js/writing_samples.js");
-  writing_samples_js.write(b"const WRITING_SAMPLES = ").unwrap();
-  writing_samples_js.write_all(&writing_samples).unwrap();
-
-  rocket::ignite()
-    .register(catchers![not_found])
-    .mount("/", routes![index, resume, css, js])
-    .launch();
+  export::write_data_scripts(&config, templates.catalogs(), &writing_samples, &source_dirs)
+    .unwrap_or_else(|err| panic!("Could not write data scripts to {}: {}", config.paths.scripts, err));
+  themes::write_stylesheet(&config)
+    .unwrap_or_else(|err| panic!("Could not write the themes to {}: {}", config.paths.styles, err));
+  let manifest = Manifest::build(assets::ROOTS)
+    .expect("Could not fingerprint assets");
+  compression::precompress(assets::ROOTS)
+    .expect("Could not precompress assets");
+
+  let args = env::args().collect::<Vec<String>>();
+  if args.get(1).map(String::as_str) == Some("animate") {
+    let settings = scroll::Settings::from_args(&args[2..], &config.animation)
+      .unwrap_or_else(|err| panic!("{}", err));
+    scroll::export(&settings, &config, &writing_samples, &source_dirs)
+      .unwrap_or_else(|err| panic!("Could not write {}: {}", settings.out_file, err));
+    return;
+  }
+
+  let export_dir = config.paths.export.clone();
+  let server = rocket(config, templates, resume, writing_samples, source_dirs, manifest);
+
+  if args.get(1).map(String::as_str) == Some("export") {
+    let out_dir = args.get(2).unwrap_or(&export_dir);
+    export::export(out_dir, &server)
+      .expect("Static export failed");
+    return;
+  }
+
+  server.launch();
+}
+
+fn rocket(
+  config: SiteConfig,
+  templates: Templates,
+  resume: Resume,
+  writing_samples: Vec<Sample>,
+  source_dirs: Vec<SourceCodeDirectory>,
+  manifest: Manifest,
+) -> Rocket {
+  let search_index = search::Index::build(&writing_samples, &source_dirs);
+  let resume_pdf = resume::pdf::render(&resume)
+    .unwrap_or_else(|err| panic!("Could not render the resume to PDF: {}", err));
+  let contact = Contact::new(&config.site, &resume);
+
+  let mut server = rocket::ignite();
+  for root in assets::ROOTS {
+    let asset_root = assets::AssetRoot::new(root)
+      .unwrap_or_else(|err| panic!("Could not serve assets from {}: {}", root, err));
+    server = server.mount(&format!("/{}", root), asset_root);
+  }
+
+  // Response fairings run in the order they're attached. Compression comes
+  // after anything that rewrites the body, and caching after compression, so
+  // each encoding is hashed as it's sent and gets an ETag of its own. The
+  // sitemap is built from the routes mounted before it.
+  server
+    .manage(writing_samples)
+    .manage(source_dirs)
+    .manage(search_index)
+    .manage(manifest)
+    .manage(templates)
+    .manage(config)
+    .manage(resume)
+    .manage(resume::Pdf(resume_pdf))
+    .manage(contact)
+    .manage(preview::Previews::default())
+    .attach(redirects::Redirects)
+    .attach(security::SecurityHeaders)
+    .attach(themes::Themes)
+    .attach(compression::Compression)
+    .attach(caching::Caching)
+    .register(catchers![not_found, server_error])
+    .mount("/", routes![
+      index,
+      resume::page,
+      resume::text,
+      resume::markdown,
+      resume::json,
+      resume::pdf,
+      contact::vcard,
+      preview::image,
+      api::writing,
+      api::search,
+      feed::atom_feed,
+      feed::rss_feed,
+      pages::writing::index,
+      pages::writing::article,
+      pages::code::index,
+      pages::code::source,
+      pages::transcript::transcript,
+    ])
+    .attach(sitemap::Crawling)
 }
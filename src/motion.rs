@@ -0,0 +1,85 @@
+use rocket::{Outcome, Request};
+use rocket::http::{Cookie, SameSite};
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder};
+
+use compression;
+
+
+const COOKIE: &str = "motion";
+// The client hint for `prefers-reduced-motion`, which browsers that support
+// client hints send once asked to with `Accept-CH`.
+const HINT: &str = "Sec-CH-Prefers-Reduced-Motion";
+
+// Whether the index page animates. `?motion=reduce` or `?motion=full` decides,
+// and is remembered in a cookie; failing those, so does the client hint.
+// Without any of them the script checks `prefers-reduced-motion` itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+  Default,
+  Full,
+  Reduced,
+}
+
+impl Motion {
+  fn parse(value: &str) -> Option<Motion> {
+    match value {
+      "reduce"        => Some(Motion::Reduced),
+      "full"          => Some(Motion::Full),
+      "no-preference" => Some(Motion::Default),
+      _               => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Motion::Default => "default",
+      Motion::Full    => "full",
+      Motion::Reduced => "reduce",
+    }
+  }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Motion {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Motion, ()> {
+    let chosen = request
+      .get_query_value::<String>("motion")
+      .and_then(Result::ok)
+      .and_then(|value| Motion::parse(&value));
+    if let Some(motion) = chosen {
+      let cookie = Cookie::build(COOKIE, motion.as_str())
+        .path("/")
+        .same_site(SameSite::Lax)
+        .permanent()
+        .finish();
+      request.cookies().add(cookie);
+      return Outcome::Success(motion);
+    }
+
+    let remembered = request
+      .cookies()
+      .get(COOKIE)
+      .and_then(|cookie| Motion::parse(cookie.value()));
+    let hinted = request
+      .headers()
+      .get_one(HINT)
+      .and_then(Motion::parse);
+
+    Outcome::Success(remembered.or(hinted).unwrap_or(Motion::Default))
+  }
+}
+
+// A page that differs by motion, which caches have to key on.
+pub struct ByMotion<R>(pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for ByMotion<R> {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = self.0.respond_to(request)?;
+    compression::add_vary(&mut response, "Cookie");
+    compression::add_vary(&mut response, HINT);
+    response.set_raw_header("Accept-CH", HINT);
+    Ok(response)
+  }
+}
@@ -0,0 +1,128 @@
+use std::io;
+
+use rocket::State;
+use rocket::response::content::Html;
+use serde_json::Value;
+use tera::Context;
+
+use code::{Line, Source, SourceCodeDirectory};
+use fingerprint::Manifest;
+use highlight::{self, language_title};
+use i18n::{ByLocale, Locale};
+use templates::Templates;
+
+
+#[get("/code")]
+pub fn index(
+  locale: Locale,
+  source_dirs: State<Vec<SourceCodeDirectory>>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<ByLocale<Html<String>>, io::Error> {
+  Ok(ByLocale(Html(manifest.rewrite_html(&index_page(&templates, &source_dirs, &locale)?))))
+}
+
+#[get("/code/<language>/<file>")]
+pub fn source(
+  language: String,
+  file: String,
+  locale: Locale,
+  source_dirs: State<Vec<SourceCodeDirectory>>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<Option<ByLocale<Html<String>>>, io::Error> {
+  let page = source_page(&templates, &source_dirs, &language, &file, &locale)?;
+  Ok(page.map(|page| ByLocale(Html(manifest.rewrite_html(&page)))))
+}
+
+pub fn index_page(templates: &Templates, source_dirs: &[SourceCodeDirectory], locale: &str) -> Result<String, io::Error> {
+  let dirs = source_dirs
+    .iter()
+    .map(|dir| json!({
+      "language": dir.language_name,
+      "title": language_title(&dir.language_name),
+      "sources": dir.source_files.iter().map(|source| json!({
+        "file_name": source.file_name,
+        "name": display_name(source),
+        "origin": source.provenance().map(|provenance| format!("{}/{}", provenance.owner, provenance.repository)),
+        "line_count": source.lines_of_code.len(),
+      })).collect::<Vec<Value>>(),
+    }))
+    .collect::<Vec<Value>>();
+
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "nav.code"));
+  super::share(&mut context, "/code");
+  context.insert("dirs", &dirs);
+  templates.render("code/index.html", context, locale)
+}
+
+pub fn source_page(
+  templates: &Templates,
+  source_dirs: &[SourceCodeDirectory],
+  language: &str,
+  file: &str,
+  locale: &str,
+) -> Result<Option<String>, io::Error> {
+  let source = source_dirs
+    .iter()
+    .find(|dir| dir.language_name == language)
+    .and_then(|dir| dir.source_files.iter().find(|source| source.file_name == file));
+  let source = match source {
+    Some(source) => source,
+    None         => return Ok(None),
+  };
+
+  let provenance = source.provenance().map(|provenance| json!({
+    "path": provenance.path,
+    "url": provenance.url(),
+    "owner": provenance.owner,
+    "repository": provenance.repository,
+  }));
+  let lines = source.lines_of_code
+    .iter()
+    .map(|line| render_line(language, line))
+    .collect::<Vec<Value>>();
+
+  let mut context = Context::new();
+  context.insert("title", &display_name(source));
+  context.insert("description", &description(language, source));
+  super::share(&mut context, &format!("/code/{}/{}", language, source.file_name));
+  context.insert("provenance", &provenance);
+  context.insert("language_title", &language_title(language));
+  context.insert("line_count", &source.lines_of_code.len());
+  context.insert("lines", &lines);
+  templates.render("code/source.html", context, locale).map(Some)
+}
+
+fn description(language: &str, source: &Source) -> String {
+  let origin = source
+    .provenance()
+    .map(|provenance| format!(" from {}/{}", provenance.owner, provenance.repository))
+    .unwrap_or_default();
+  format!("{} lines of {}{}.", source.lines_of_code.len(), language_title(language), origin)
+}
+
+pub fn display_name(source: &Source) -> String {
+  match source.provenance() {
+    Some(provenance) => provenance.path.rsplit('/').next().unwrap().to_string(),
+    None             => source.file_name.clone(),
+  }
+}
+
+// A line with its number, depth of indentation and highlighted tokens.
+pub fn render_line(language: &str, line: &Line) -> Value {
+  let tokens = highlight::highlight(language, line.code.trim_start())
+    .into_iter()
+    .map(|(class, text)| json!({
+      "class": class.css_class(),
+      "text": text,
+    }))
+    .collect::<Vec<Value>>();
+
+  json!({
+    "number": line.number + 1,
+    "indent": line.indent,
+    "tokens": tokens,
+  })
+}
@@ -0,0 +1,84 @@
+pub mod code;
+pub mod transcript;
+pub mod writing;
+
+use std::io;
+
+use tera::Context;
+
+use contact::Contact;
+use export;
+use motion::Motion;
+use preview;
+use resume::Resume;
+use segments::Frame;
+use templates::Templates;
+
+
+pub fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&'  => escaped.push_str("&amp;"),
+      '<'  => escaped.push_str("&lt;"),
+      '>'  => escaped.push_str("&gt;"),
+      '"'  => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _    => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+// Every page names its canonical URL, and the preview shown when a link to
+// it is shared.
+fn share(context: &mut Context, path: &str) {
+  context.insert("canonical_path", path);
+  context.insert("preview", &preview::image_path(path));
+}
+
+pub fn index_page(
+  templates: &Templates,
+  contact: &Contact,
+  frame: &Frame,
+  motion: Motion,
+  locale: &str,
+) -> Result<String, io::Error> {
+  // `</` can't appear inside a script element, even one holding data.
+  let json_ld = contact.to_json_ld().to_string().replace("</", "<\\/");
+
+  let mut context = Context::new();
+  share(&mut context, "/");
+  context.insert("contact", contact);
+  context.insert("json_ld", &json_ld);
+  context.insert("frame", frame);
+  context.insert("motion", motion.as_str());
+  context.insert("writing_samples", &export::writing_samples_script(templates.catalogs(), locale));
+  templates.render("index.html", context, locale)
+}
+
+pub fn resume_page(templates: &Templates, resume: &Resume, locale: &str) -> Result<String, io::Error> {
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "nav.resume"));
+  share(&mut context, "/resume");
+  context.insert("basics", &resume.basics);
+  context.insert("links", &resume.links());
+  context.insert("sections", &resume.sections());
+  templates.render("resume.html", context, locale)
+}
+
+pub fn not_found(templates: &Templates, path: &str, locale: &str) -> Result<String, io::Error> {
+  let nothing_at = escape(&templates.message(locale, "errors.nothing_at"))
+    .replace("{path}", &format!("<code>{}</code>", escape(path)));
+
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "errors.not_found"));
+  context.insert("nothing_at", &nothing_at);
+  templates.render("errors/404.html", context, locale)
+}
+
+pub fn server_error(templates: &Templates, locale: &str) -> Result<String, io::Error> {
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "errors.server_error"));
+  templates.render("errors/500.html", context, locale)
+}
@@ -0,0 +1,105 @@
+use std::io;
+
+use rocket::State;
+use rocket::request::LenientForm;
+use rocket::response::content::Html;
+use serde_json::Value;
+use tera::Context;
+
+use code::SourceCodeDirectory;
+use config::SiteConfig;
+use fingerprint::Manifest;
+use highlight::language_title;
+use i18n::{ByLocale, Locale};
+use segments::{self, Frame};
+use templates::Templates;
+use writing::Sample;
+
+
+// What the index page's animation is showing, as `js/main.js` links to it.
+// Anything left out is taken from the first frame.
+#[derive(Debug, Default, FromForm)]
+pub struct Showing {
+  pub language: Option<String>,
+  pub file: Option<String>,
+  pub code_line: Option<usize>,
+  pub slug: Option<String>,
+  pub writing_line: Option<usize>,
+}
+
+#[get("/transcript?<showing..>")]
+#[allow(clippy::too_many_arguments)]
+pub fn transcript(
+  showing: LenientForm<Showing>,
+  locale: Locale,
+  frame: Frame,
+  config: State<SiteConfig>,
+  samples: State<Vec<Sample>>,
+  source_dirs: State<Vec<SourceCodeDirectory>>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<Option<ByLocale<Html<String>>>, io::Error> {
+  let page = transcript_page(&templates, &config, &samples, &source_dirs, &frame, &showing, &locale)?;
+  Ok(page.map(|page| ByLocale(Html(manifest.rewrite_html(&page)))))
+}
+
+pub fn transcript_page(
+  templates: &Templates,
+  config: &SiteConfig,
+  samples: &[Sample],
+  source_dirs: &[SourceCodeDirectory],
+  frame: &Frame,
+  showing: &Showing,
+  locale: &str,
+) -> Result<Option<String>, io::Error> {
+  let window = config.animation.window_lines as usize;
+  let language = showing.language.as_ref().unwrap_or(&frame.language);
+  let file = showing.file.as_ref().unwrap_or(&frame.file_name);
+  let slug = showing.slug.as_ref().unwrap_or(&frame.slug);
+
+  let source = source_dirs
+    .iter()
+    .find(|dir| dir.language_name == *language)
+    .and_then(|dir| dir.source_files.iter().find(|source| source.file_name == *file));
+  let sample = samples
+    .iter()
+    .find(|sample| sample.slug == *slug)
+    .map(|sample| sample.in_language(locale));
+  let (source, sample) = match (source, sample) {
+    (Some(source), Some(sample)) => (source, sample),
+    _                            => return Ok(None),
+  };
+
+  let code_line = showing.code_line.unwrap_or(0).min(source.lines_of_code.len());
+  let writing_line = showing.writing_line.unwrap_or(0).min(sample.lines.len());
+  let code = json!({
+    "description": segments::describe_source(source),
+    "language_title": language_title(language),
+    "url": format!("/code/{}/{}", language, source.file_name),
+    "lines": source.lines_of_code
+      .iter()
+      .skip(code_line)
+      .take(window)
+      .map(|line| super::code::render_line(language, line))
+      .collect::<Vec<Value>>(),
+  });
+  let writing = json!({
+    "title": sample.title,
+    "url": format!("/writing/{}", sample.slug),
+    "lines": sample.lines
+      .iter()
+      .skip(writing_line)
+      .take(window)
+      .map(|line| &line.text)
+      .filter(|text| !text.trim().is_empty())
+      .collect::<Vec<&String>>(),
+  });
+
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "transcript.title"));
+  context.insert("description", &templates.message(locale, "transcript.description"));
+  super::share(&mut context, "/transcript");
+  context.insert("code", &code);
+  context.insert("writing", &writing);
+  templates.render("transcript.html", context, locale).map(Some)
+}
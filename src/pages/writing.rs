@@ -0,0 +1,96 @@
+use std::io;
+
+use rocket::State;
+use rocket::response::content::Html;
+use serde_json::Value;
+use tera::Context;
+
+use fingerprint::Manifest;
+use i18n::{ByLocale, Locale};
+use templates::Templates;
+use writing::{self, Sample};
+
+
+#[get("/writing")]
+pub fn index(
+  locale: Locale,
+  samples: State<Vec<Sample>>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<ByLocale<Html<String>>, io::Error> {
+  Ok(ByLocale(Html(manifest.rewrite_html(&index_page(&templates, &samples, &locale)?))))
+}
+
+#[get("/writing/<slug>")]
+pub fn article(
+  slug: String,
+  locale: Locale,
+  samples: State<Vec<Sample>>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<Option<ByLocale<Html<String>>>, io::Error> {
+  let page = article_page(&templates, &samples, &slug, &locale)?;
+  Ok(page.map(|page| ByLocale(Html(manifest.rewrite_html(&page)))))
+}
+
+// Samples are listed in `locale` where they've been translated into it.
+pub fn index_page(templates: &Templates, samples: &[Sample], locale: &str) -> Result<String, io::Error> {
+  let mut series = Vec::new();
+
+  let mut samples = samples.iter().peekable();
+  while let Some(first) = samples.next() {
+    let mut entries = vec![entry(first.in_language(locale))];
+    while let Some(sample) = samples.next_if(|sample| sample.series == first.series) {
+      entries.push(entry(sample.in_language(locale)));
+    }
+    series.push(entries);
+  }
+
+  let mut context = Context::new();
+  context.insert("title", &templates.message(locale, "nav.writing"));
+  super::share(&mut context, "/writing");
+  context.insert("series", &series);
+  templates.render("writing/index.html", context, locale)
+}
+
+pub fn article_page(templates: &Templates, samples: &[Sample], slug: &str, locale: &str) -> Result<Option<String>, io::Error> {
+  let original = match samples.iter().find(|sample| sample.slug == slug) {
+    Some(sample) => sample,
+    None         => return Ok(None),
+  };
+  let (previous, next) = writing::neighbours(samples, original);
+  let sample = original.in_language(locale);
+  let content_lang = sample
+    .language
+    .as_deref()
+    .unwrap_or_else(|| templates.catalogs().default_language());
+
+  let mut context = Context::new();
+  context.insert("title", &sample.title);
+  context.insert("description", &sample.excerpt());
+  context.insert("og_type", "article");
+  super::share(&mut context, &format!("/writing/{}", sample.slug));
+  context.insert("reading_minutes", &sample.stats.reading_minutes);
+  context.insert("word_count", &sample.stats.word_count);
+  context.insert("character_count", &sample.stats.character_count);
+  context.insert("paragraphs", &sample.annotated_paragraphs());
+  context.insert("content_lang", content_lang);
+  context.insert("previous", &previous.map(|sample| link(sample.in_language(locale))));
+  context.insert("next", &next.map(|sample| link(sample.in_language(locale))));
+  templates.render("writing/article.html", context, locale).map(Some)
+}
+
+fn entry(sample: &Sample) -> Value {
+  json!({
+    "slug": sample.slug,
+    "title": sample.title,
+    "reading_minutes": sample.stats.reading_minutes,
+  })
+}
+
+fn link(sample: &Sample) -> Value {
+  json!({
+    "slug": sample.slug,
+    "title": sample.title,
+  })
+}
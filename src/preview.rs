@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use rocket::State;
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+
+use code::{self, SourceCodeDirectory};
+use config::SiteConfig;
+use pages;
+use raster::{self, Palette, Segments};
+use segments;
+use writing::{self, Sample};
+
+
+// The size OpenGraph and Twitter show large previews at.
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+const TITLE_SIZE: f32 = 52.0;
+const SUBTITLE_SIZE: f32 = 26.0;
+
+#[get("/preview/<path..>")]
+pub fn image(
+  path: PathBuf,
+  previews: State<Previews>,
+  config: State<SiteConfig>,
+  samples: State<Vec<Sample>>,
+  source_dirs: State<Vec<SourceCodeDirectory>>,
+) -> Result<Option<Content<Vec<u8>>>, io::Error> {
+  let page = match path.to_str().and_then(page_path) {
+    Some(page) => page,
+    None       => return Ok(None),
+  };
+  let png = previews.get(&page, &config, &samples, &source_dirs)?;
+  Ok(png.map(|png| Content(ContentType::PNG, png)))
+}
+
+// The URL of the preview for the page at `path`.
+pub fn image_path(path: &str) -> String {
+  match path {
+    "/"  => "/preview/index.png".to_string(),
+    path => format!("/preview{}.png", path),
+  }
+}
+
+fn page_path(image: &str) -> Option<String> {
+  match image.trim_end_matches(".png") {
+    _ if !image.ends_with(".png") => None,
+    "index"                       => Some("/".to_string()),
+    page                          => Some(format!("/{}", page)),
+  }
+}
+
+// Every page with a preview, by path.
+pub fn pages(samples: &[Sample], source_dirs: &[SourceCodeDirectory]) -> Vec<String> {
+  let mut pages = vec!["/", "/resume", "/writing", "/code", "/transcript"]
+    .into_iter()
+    .map(String::from)
+    .collect::<Vec<String>>();
+  pages.extend(samples.iter().map(|sample| format!("/writing/{}", sample.slug)));
+  for dir in source_dirs {
+    pages.extend(dir.source_files.iter().map(|source| format!("/code/{}/{}", dir.language_name, source.file_name)));
+  }
+
+  pages
+}
+
+// What a preview shows: the page's title over the code and writing it's
+// about, or the first of each for pages about neither.
+pub struct Scene<'a> {
+  pub title: String,
+  pub subtitle: String,
+  pub code: &'a [code::Line],
+  pub writing: &'a [writing::Line],
+}
+
+impl<'a> Scene<'a> {
+  pub fn for_page(
+    path: &str,
+    config: &SiteConfig,
+    samples: &'a [Sample],
+    source_dirs: &'a [SourceCodeDirectory],
+  ) -> Option<Scene<'a>> {
+    let first_source = source_dirs.iter().flat_map(|dir| dir.source_files.first()).next();
+    let mut scene = Scene {
+      title: config.site.title.clone(),
+      subtitle: config.site.description.clone(),
+      code: first_source.map(|source| &source.lines_of_code[..]).unwrap_or(&[]),
+      writing: samples.first().map(|sample| &sample.lines[..]).unwrap_or(&[]),
+    };
+
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<&str>>();
+    match &segments[..] {
+      [""]                 => {},
+      ["resume"]           => scene.title = "Résumé".to_string(),
+      ["writing"]          => scene.title = "Writing".to_string(),
+      ["code"]             => scene.title = "Code".to_string(),
+      ["transcript"]       => scene.title = "Transcript".to_string(),
+      ["writing", slug]    => {
+        let sample = samples.iter().find(|sample| sample.slug == *slug)?;
+        scene.title = sample.title.clone();
+        scene.writing = &sample.lines;
+      },
+      ["code", language, file] => {
+        let source = source_dirs
+          .iter()
+          .find(|dir| dir.language_name == *language)
+          .and_then(|dir| dir.source_files.iter().find(|source| source.file_name == *file))?;
+        scene.title = pages::code::display_name(source);
+        scene.code = &source.lines_of_code;
+      },
+      _                    => return None,
+    }
+    if scene.title != config.site.title {
+      scene.subtitle = config.site.title.clone();
+    }
+
+    Some(scene)
+  }
+}
+
+// Previews are rendered the first time they're asked for and kept. The lock
+// is only held to look them up and keep them, so a slow render doesn't hold
+// up previews that are ready; two requests for a new one may both render it.
+#[derive(Default)]
+pub struct Previews {
+  rendered: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl Previews {
+  pub fn get(
+    &self,
+    path: &str,
+    config: &SiteConfig,
+    samples: &[Sample],
+    source_dirs: &[SourceCodeDirectory],
+  ) -> Result<Option<Vec<u8>>, io::Error> {
+    if let Some(png) = self.rendered().get(path) {
+      return Ok(Some(png.clone()));
+    }
+
+    let scene = match Scene::for_page(path, config, samples, source_dirs) {
+      Some(scene) => scene,
+      None        => return Ok(None),
+    };
+    let png = render(&scene, config)?;
+    self.rendered().insert(path.to_string(), png.clone());
+
+    Ok(Some(png))
+  }
+
+  // Nothing panics while the lock is held, but if it ever did, the cache
+  // would still be sound.
+  fn rendered(&self) -> MutexGuard<HashMap<String, Vec<u8>>> {
+    self.rendered
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+// Draw the index page's diagonal segments in the light theme, with text laid
+// out as in their first frame, and the title between them.
+pub fn render(scene: &Scene, config: &SiteConfig) -> Result<Vec<u8>, io::Error> {
+  let title_font = raster::bold_font()?;
+  let subtitle_font = raster::regular_font()?;
+  let palette = Palette::from_theme(&config.themes[&config.theme.light]);
+  let segments = Segments::new(WIDTH, HEIGHT, palette)?;
+  let mut canvas = segments.draw(
+    &segments::code_lines(&config.animation, scene.code),
+    &segments::writing_lines(&config.animation, scene.writing));
+
+  let (width, height) = (WIDTH as f32, HEIGHT as f32);
+  let title = raster::fit(&title_font, TITLE_SIZE, &scene.title, segments.gap());
+  let subtitle = raster::fit(&subtitle_font, SUBTITLE_SIZE, &scene.subtitle, segments.gap());
+  let title_x = (width - raster::advance(&title_font, TITLE_SIZE, &title)) / 2.0;
+  let subtitle_x = (width - raster::advance(&subtitle_font, SUBTITLE_SIZE, &subtitle)) / 2.0;
+  canvas.text(&title_font, TITLE_SIZE, (title_x, height / 2.0), &title, palette.writing_text, None);
+  canvas.text(&subtitle_font, SUBTITLE_SIZE, (subtitle_x, height / 2.0 + SUBTITLE_SIZE * 1.6), &subtitle, palette.writing_text, None);
+
+  canvas.to_png()
+}
@@ -0,0 +1,282 @@
+use std::io;
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use png::{BitDepth, ColorType, Encoder};
+
+use config::Theme;
+use segments::Text;
+
+
+const CODE_FONT: &[u8] = include_bytes!("../fonts/DejaVuSansMono.ttf");
+const WRITING_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+// Each segment spans this much of the width, overlapping the other so the
+// space left between them runs diagonally.
+pub const SEGMENT_WIDTH: f32 = 0.55;
+// Text in the segments is sized in the units of their 100 by 100 viewBox.
+pub const SEGMENT_FONT_SIZE: f32 = 2.4;
+
+pub type Rgb = [u8; 3];
+
+// A theme's colours, where `css/main.css` uses them for the segments in
+// `templates/index.html`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+  pub background: Rgb,
+  pub code_fill: Rgb,
+  pub code_text: Rgb,
+  pub writing_fill: Rgb,
+  pub writing_text: Rgb,
+}
+
+impl Palette {
+  pub fn from_theme(theme: &Theme) -> Palette {
+    Palette {
+      background: rgb(&theme.background),
+      code_fill: rgb(&theme.accent),
+      code_text: rgb(&theme.code_text),
+      writing_fill: rgb(&theme.surface),
+      writing_text: rgb(&theme.muted),
+    }
+  }
+}
+
+// A colour the settings have already checked is hex, short or long, leaving
+// out any alpha.
+fn rgb(hex: &str) -> Rgb {
+  let digits = hex.trim_start_matches('#');
+  let channel = |i: usize| {
+    let parsed = match digits.len() {
+      3 | 4 => u8::from_str_radix(&digits[i..i + 1], 16).map(|digit| digit * 0x11),
+      _     => u8::from_str_radix(&digits[2 * i..2 * i + 2], 16),
+    };
+    parsed.unwrap_or(0)
+  };
+  [channel(0), channel(1), channel(2)]
+}
+
+pub fn regular_font() -> Result<FontRef<'static>, io::Error> {
+  load_font(WRITING_FONT)
+}
+
+pub fn bold_font() -> Result<FontRef<'static>, io::Error> {
+  load_font(BOLD_FONT)
+}
+
+fn load_font(data: &'static [u8]) -> Result<FontRef<'static>, io::Error> {
+  FontRef::try_from_slice(data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+pub fn advance(font: &FontRef, size: f32, text: &str) -> f32 {
+  let font = font.as_scaled(PxScale::from(size));
+  text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum()
+}
+
+// Shorten `text` at a word boundary until it fits in `width`.
+pub fn fit(font: &FontRef, size: f32, text: &str, width: f32) -> String {
+  if advance(font, size, text) <= width {
+    return text.to_string();
+  }
+
+  let mut words = text.split_whitespace().collect::<Vec<&str>>();
+  while words.len() > 1 {
+    words.pop();
+    let shortened = format!("{}…", words.join(" "));
+    if advance(font, size, &shortened) <= width {
+      return shortened;
+    }
+  }
+  format!("{}…", words.concat())
+}
+
+// The index page's diagonal segments at a given size, ready to have lines of
+// code and writing drawn into them.
+pub struct Segments {
+  width: u32,
+  height: u32,
+  code: Triangle,
+  writing: Triangle,
+  backdrop: Canvas,
+  palette: Palette,
+  code_font: FontRef<'static>,
+  writing_font: FontRef<'static>,
+}
+
+impl Segments {
+  pub fn new(width: u32, height: u32, palette: Palette) -> Result<Segments, io::Error> {
+    let (w, h) = (width as f32, height as f32);
+    let code = Triangle([(0.0, 0.0), (SEGMENT_WIDTH * w, 0.0), (0.0, h)]);
+    let writing = Triangle([(w, h), (w, 0.0), (w - SEGMENT_WIDTH * w, h)]);
+
+    let mut backdrop = Canvas::new(width, height, palette.background);
+    backdrop.fill(&code, palette.code_fill);
+    backdrop.fill(&writing, palette.writing_fill);
+
+    Ok(Segments {
+      width,
+      height,
+      code,
+      writing,
+      backdrop,
+      palette,
+      code_font: load_font(CODE_FONT)?,
+      writing_font: load_font(WRITING_FONT)?,
+    })
+  }
+
+  // The width of the space between the segments, across the middle.
+  pub fn gap(&self) -> f32 {
+    (1.0 - SEGMENT_WIDTH) * self.width as f32
+  }
+
+  pub fn draw(&self, code: &[Text], writing: &[Text]) -> Canvas {
+    let (scale_x, scale_y) = (SEGMENT_WIDTH * self.width as f32 / 100.0, self.height as f32 / 100.0);
+    let font_size = SEGMENT_FONT_SIZE * scale_y;
+    let writing_left = self.gap();
+    let mut canvas = self.backdrop.clone();
+
+    for line in code {
+      let origin = (line.x as f32 * scale_x, line.y as f32 * scale_y);
+      canvas.text(&self.code_font, font_size, origin, &line.text, self.palette.code_text, Some(&self.code));
+    }
+    for line in writing {
+      let origin = (writing_left + line.x as f32 * scale_x, line.y as f32 * scale_y);
+      canvas.text(&self.writing_font, font_size, origin, &line.text, self.palette.writing_text, Some(&self.writing));
+    }
+
+    canvas
+  }
+}
+
+#[derive(Clone)]
+pub struct Triangle([(f32, f32); 3]);
+
+impl Triangle {
+  fn contains(&self, x: f32, y: f32) -> bool {
+    let [a, b, c] = self.0;
+    let side = |(x1, y1): (f32, f32), (x2, y2): (f32, f32)| (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+    let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+  }
+}
+
+#[derive(Clone)]
+pub struct Canvas {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+impl Canvas {
+  fn new(width: u32, height: u32, background: Rgb) -> Canvas {
+    Canvas {
+      width,
+      height,
+      pixels: background.iter().cloned().cycle().take((width * height * 3) as usize).collect(),
+    }
+  }
+
+  fn blend(&mut self, x: i32, y: i32, color: Rgb, coverage: f32) {
+    if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || coverage <= 0.0 {
+      return;
+    }
+
+    let offset = ((y as u32 * self.width + x as u32) * 3) as usize;
+    let coverage = coverage.min(1.0);
+    for (channel, value) in self.pixels[offset..offset + 3].iter_mut().zip(color.iter()) {
+      *channel = (*channel as f32 * (1.0 - coverage) + *value as f32 * coverage).round() as u8;
+    }
+  }
+
+  // Edges are smoothed by sampling each pixel at four points.
+  fn fill(&mut self, triangle: &Triangle, color: Rgb) {
+    for y in 0..self.height as i32 {
+      for x in 0..self.width as i32 {
+        let covered = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+          .iter()
+          .filter(|(dx, dy)| triangle.contains(x as f32 + dx, y as f32 + dy))
+          .count();
+        self.blend(x, y, color, covered as f32 / 4.0);
+      }
+    }
+  }
+
+  // Draw `text` with its baseline starting at `origin`, leaving out any of it
+  // that falls outside `clip`.
+  pub fn text(&mut self, font: &FontRef, size: f32, origin: (f32, f32), text: &str, color: Rgb, clip: Option<&Triangle>) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut x = origin.0;
+
+    for c in text.chars() {
+      let id = scaled.glyph_id(c);
+      let glyph = id.with_scale_and_position(scale, point(x, origin.1));
+      x += scaled.h_advance(id);
+      if x > self.width as f32 + size {
+        break;
+      }
+
+      if let Some(outline) = font.outline_glyph(glyph) {
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+          let (px, py) = (bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32);
+          if clip.map(|clip| clip.contains(px as f32 + 0.5, py as f32 + 0.5)).unwrap_or(true) {
+            self.blend(px, py, color, coverage);
+          }
+        });
+      }
+    }
+  }
+
+  pub fn to_png(&self) -> Result<Vec<u8>, io::Error> {
+    let mut png = Vec::new();
+    {
+      let mut encoder = Encoder::new(&mut png, self.width, self.height);
+      encoder.set_color(ColorType::Rgb);
+      encoder.set_depth(BitDepth::Eight);
+      encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&self.pixels))
+        .map_err(to_io_error)?;
+    }
+
+    Ok(png)
+  }
+}
+
+// An animated PNG that loops forever, from frames of the same size and how
+// many milliseconds each is shown for.
+pub fn to_apng(frames: &[(Canvas, u32)]) -> Result<Vec<u8>, io::Error> {
+  let (width, height) = match frames.first() {
+    Some((canvas, _)) => (canvas.width, canvas.height),
+    None              => return Err(io::Error::new(io::ErrorKind::InvalidInput, "An animation needs at least one frame")),
+  };
+
+  let mut png = Vec::new();
+  {
+    let mut encoder = Encoder::new(&mut png, width, height);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(to_io_error)?;
+
+    let mut writer = encoder.write_header().map_err(to_io_error)?;
+    for (canvas, millis) in frames {
+      // Delays are a fraction of a second, each half at most 16 bits.
+      let (numerator, denominator) = match *millis {
+        millis if millis <= u32::from(u16::MAX) => (millis as u16, 1000),
+        millis                                  => ((millis / 100).min(u32::from(u16::MAX)) as u16, 10),
+      };
+      writer.set_frame_delay(numerator, denominator).map_err(to_io_error)?;
+      writer.write_image_data(&canvas.pixels).map_err(to_io_error)?;
+    }
+    writer.finish().map_err(to_io_error)?;
+  }
+
+  Ok(png)
+}
+
+fn to_io_error(err: png::EncodingError) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
@@ -0,0 +1,563 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rocket::{Data, Request, Rocket, Route, State};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::handler::{Handler, Outcome};
+use rocket::http::{Method, Status};
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use toml;
+
+
+pub const DEFAULT_FILE: &str = "redirects.toml";
+
+// Checked before any other route, so a rule can take over a URL that would
+// otherwise be served.
+const RANK: isize = -100;
+// No chain of rules should need more hops than this to settle.
+const MAX_HOPS: usize = 16;
+
+#[derive(Deserialize)]
+struct RulesFile {
+  #[serde(default)]
+  redirect: Vec<RuleConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+  from: String,
+  to: String,
+  kind: Option<MatchKind>,
+  status: Option<u16>,
+  #[serde(default)]
+  alias: bool,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MatchKind {
+  Exact,
+  Prefix,
+  Pattern,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+  Literal(String),
+  Param(String),
+  Rest(String),
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+  Exact(String),
+  Prefix(String),
+  Pattern(Vec<Segment>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+  Redirect(Status),
+  // Serve the target's content at the old URL without redirecting.
+  Alias,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+  from: String,
+  matcher: Matcher,
+  to: String,
+  action: Action,
+}
+
+impl Rule {
+  fn from_config(config: RuleConfig) -> Result<Rule, String> {
+    if !config.from.starts_with('/') {
+      return Err(format!("Redirect source `{}` must be a path", config.from));
+    }
+    let external = config.to.starts_with("https://") || config.to.starts_with("http://");
+    if !config.to.starts_with('/') && !external {
+      return Err(format!("Redirect target `{}` must be a path or URL", config.to));
+    }
+
+    let action = match (config.alias, config.status) {
+      (true, Some(_))          => return Err(format!("Alias `{}` can't also set a status", config.from)),
+      (true, None) if external => return Err(format!("Alias `{}` must point at a path on this site", config.from)),
+      (true, None)             => Action::Alias,
+      (false, None)            => Action::Redirect(Status::MovedPermanently),
+      (false, Some(301))       => Action::Redirect(Status::MovedPermanently),
+      (false, Some(302))       => Action::Redirect(Status::Found),
+      (false, Some(status))    => return Err(format!("Redirect `{}` has status {}, expected 301 or 302", config.from, status)),
+    };
+
+    let matcher = match config.kind.unwrap_or(MatchKind::Exact) {
+      MatchKind::Exact   => Matcher::Exact(config.from.clone()),
+      MatchKind::Prefix  => Matcher::Prefix(config.from.clone()),
+      MatchKind::Pattern => Matcher::Pattern(parse_pattern(&config.from)?),
+    };
+
+    // Every placeholder in the target has to be captured by the source.
+    let captured = match matcher {
+      Matcher::Pattern(ref segments) => segments.iter().filter_map(placeholder_name).collect(),
+      _                              => Vec::new(),
+    };
+    for name in placeholders(&config.to) {
+      if !captured.contains(&name) {
+        return Err(format!("Redirect target `{}` uses `{}`, which `{}` doesn't capture", config.to, name, config.from));
+      }
+    }
+
+    Ok(Rule {
+      from: config.from,
+      matcher,
+      to: config.to,
+      action,
+    })
+  }
+
+  // The target for a path, if this rule matches it.
+  fn apply(&self, path: &str) -> Option<String> {
+    match self.matcher {
+      Matcher::Exact(ref from)       => if path == from {
+        Some(self.to.clone())
+      } else {
+        None
+      },
+      Matcher::Prefix(ref from)      => if path.starts_with(from.as_str()) {
+        Some(local(format!("{}{}", self.to, &path[from.len()..])))
+      } else {
+        None
+      },
+      Matcher::Pattern(ref segments) => {
+        let mut captures = match_pattern(segments, path)?;
+        let mut to = self.to.clone();
+        // Longest names first, so `:slug` doesn't clobber part of `:slugs`.
+        captures.sort_by_key(|(name, _)| Reverse(name.len()));
+        for (name, value) in captures {
+          to = to.replace(&name, &value);
+        }
+        Some(local(to))
+      },
+    }
+  }
+
+  // Every path this rule matches, as the tokens of each way it can be
+  // spelled.
+  fn sources(&self) -> Vec<Vec<Token>> {
+    match self.matcher {
+      Matcher::Exact(ref from)       => vec![literal(from)],
+      Matcher::Prefix(ref from)      => vec![[literal(from), vec![Token::Anything]].concat()],
+      Matcher::Pattern(ref segments) => {
+        let mut path = Vec::new();
+        for segment in segments {
+          path.push(Token::Char('/'));
+          match segment {
+            Segment::Literal(literal) => path.extend(literal.chars().map(Token::Char)),
+            Segment::Param(_)         => path.extend(&[Token::SegmentChar, Token::SegmentChars]),
+            // `/docs/*rest` matches `/docs` as well as anything under it.
+            Segment::Rest(_)          => {
+              path.pop();
+              return vec![path.clone(), [path, vec![Token::Char('/'), Token::Anything]].concat()];
+            },
+          }
+        }
+        vec![path]
+      },
+    }
+  }
+
+  // Every path on this site the rule can lead to, if it leads anywhere here.
+  fn target(&self) -> Option<Vec<Token>> {
+    if !self.to.starts_with('/') {
+      return None;
+    }
+
+    let mut path = Vec::new();
+    for (index, part) in strip_query(&self.to).split('/').enumerate() {
+      if index > 0 {
+        path.push(Token::Char('/'));
+      }
+      match part.chars().next() {
+        Some(':') if part.len() > 1 => path.extend(&[Token::SegmentChar, Token::SegmentChars]),
+        Some('*') if part.len() > 1 => path.push(Token::Anything),
+        _                           => path.extend(part.chars().map(Token::Char)),
+      }
+    }
+    if let Matcher::Prefix(_) = self.matcher {
+      path.push(Token::Anything);
+    }
+
+    Some(path)
+  }
+}
+
+// Browsers take a target starting with `//` or `/\` to be on another host,
+// so the slashes that a matched path brings to the front are collapsed.
+fn local(to: String) -> String {
+  if !to.starts_with('/') {
+    return to;
+  }
+  format!("/{}", to.trim_start_matches(|c| c == '/' || c == '\\'))
+}
+
+// A character of a path, or a run of them, used to tell at load time whether
+// one rule can lead into another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+  Char(char),
+  // One character of a path segment, which is anything but `/`.
+  SegmentChar,
+  // Any number of them.
+  SegmentChars,
+  // Any number of characters at all.
+  Anything,
+}
+
+impl Token {
+  fn repeats(&self) -> bool {
+    *self == Token::SegmentChars || *self == Token::Anything
+  }
+
+  // Whether some character could be taken by both tokens.
+  fn meets(&self, other: &Token) -> bool {
+    match (*self, *other) {
+      (Token::Char(a), Token::Char(b))            => a == b,
+      (Token::Anything, _) | (_, Token::Anything) => true,
+      (Token::Char(c), _) | (_, Token::Char(c))   => c != '/',
+      _                                           => true,
+    }
+  }
+}
+
+fn literal(path: &str) -> Vec<Token> {
+  path.chars().map(Token::Char).collect()
+}
+
+// Whether any one path is spelled by both `a` and `b`, found by walking them
+// side by side.
+fn overlaps(a: &[Token], b: &[Token]) -> bool {
+  let mut seen = HashSet::new();
+  let mut queue = vec![(0, 0)];
+
+  while let Some((i, j)) = queue.pop() {
+    if !seen.insert((i, j)) {
+      continue;
+    }
+    if i == a.len() && j == b.len() {
+      return true;
+    }
+
+    // Either side can stop repeating,
+    if a.get(i).map_or(false, Token::repeats) {
+      queue.push((i + 1, j));
+    }
+    if b.get(j).map_or(false, Token::repeats) {
+      queue.push((i, j + 1));
+    }
+    // or both can take the same character.
+    if let (Some(x), Some(y)) = (a.get(i), b.get(j)) {
+      if x.meets(y) {
+        queue.push((if x.repeats() { i } else { i + 1 }, if y.repeats() { j } else { j + 1 }));
+      }
+    }
+  }
+
+  false
+}
+
+// Redirect and alias rules, loaded from a TOML file of `[[redirect]]` tables:
+//
+//   [[redirect]]
+//   from = "/cv"
+//   to = "/resume"
+//
+//   [[redirect]]
+//   kind = "pattern"
+//   from = "/blog/:year/:slug"
+//   to = "/writing/:slug"
+//   status = 302
+//
+// Exact rules win over prefix rules, which win over patterns. Among prefixes
+// the longest wins, among patterns the first listed.
+#[derive(Clone, Debug, Default)]
+pub struct Rules {
+  rules: Vec<Rule>,
+}
+
+impl Rules {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Rules, io::Error> {
+    if !path.as_ref().exists() {
+      return Ok(Rules::default());
+    }
+
+    Rules::parse(&fs::read_to_string(path)?)
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+  }
+
+  pub fn parse(config: &str) -> Result<Rules, String> {
+    let file: RulesFile = toml::from_str(config).map_err(|err| err.to_string())?;
+    let mut rules = Vec::new();
+    let mut sources = HashSet::new();
+
+    for rule in file.redirect {
+      let rule = Rule::from_config(rule)?;
+      if !sources.insert(rule.from.clone()) {
+        return Err(format!("Redirect source `{}` is listed more than once", rule.from));
+      }
+      rules.push(rule);
+    }
+
+    let rank = |rule: &Rule| match rule.matcher {
+      Matcher::Exact(_)           => (0, Reverse(0)),
+      Matcher::Prefix(ref prefix) => (1, Reverse(prefix.len())),
+      Matcher::Pattern(_)         => (2, Reverse(0)),
+    };
+    // A stable sort keeps patterns in the order they were listed.
+    rules.sort_by_key(rank);
+
+    let rules = Rules { rules };
+    rules.check_chains()?;
+    Ok(rules)
+  }
+
+  // The rule matching a path and the URL it leads to.
+  pub fn resolve(&self, path: &str) -> Option<(Action, String)> {
+    self.rules
+      .iter()
+      .filter_map(|rule| rule.apply(path).map(|to| (rule.action, to)))
+      .next()
+  }
+
+  // Rules lead into one another wherever a path one of them leads to could
+  // match another's source. Cycles among them are rejected, so that no URL
+  // can bounce around forever, as are chains too long to settle.
+  fn check_chains(&self) -> Result<(), String> {
+    let sources = self.rules.iter().map(Rule::sources).collect::<Vec<Vec<Vec<Token>>>>();
+    let leads_to = self.rules
+      .iter()
+      .map(|rule| match rule.target() {
+        Some(target) => (0..self.rules.len())
+          .filter(|&next| sources[next].iter().any(|source| overlaps(&target, source)))
+          .collect(),
+        None         => Vec::new(),
+      })
+      .collect::<Vec<Vec<usize>>>();
+
+    let mut hops = vec![None; self.rules.len()];
+    for start in 0..self.rules.len() {
+      self.longest_chain(start, &leads_to, &mut Vec::new(), &mut hops)?;
+    }
+
+    Ok(())
+  }
+
+  // The most hops a chain starting at `rule` can take, remembered in `hops`.
+  fn longest_chain(
+    &self,
+    rule: usize,
+    leads_to: &[Vec<usize>],
+    chain: &mut Vec<usize>,
+    hops: &mut [Option<usize>],
+  ) -> Result<usize, String> {
+    if let Some(known) = hops[rule] {
+      return Ok(known);
+    }
+    if let Some(start) = chain.iter().position(|&seen| seen == rule) {
+      let cycle = chain[start..]
+        .iter()
+        .chain(Some(&rule))
+        .map(|&index| self.rules[index].from.as_str())
+        .collect::<Vec<&str>>();
+      return Err(format!("Redirect rules form a cycle: {}", cycle.join(" -> ")));
+    }
+
+    chain.push(rule);
+    let mut longest = 0;
+    for &next in &leads_to[rule] {
+      longest = longest.max(self.longest_chain(next, leads_to, chain, hops)? + 1);
+    }
+    chain.pop();
+
+    if longest > MAX_HOPS {
+      return Err(format!("Redirect chain starting at `{}` is longer than {} hops", self.rules[rule].from, MAX_HOPS));
+    }
+    hops[rule] = Some(longest);
+    Ok(longest)
+  }
+
+  fn target(&self, request: &Request) -> Option<(Action, String)> {
+    let uri = request.uri();
+    let (action, to) = self.resolve(uri.path())?;
+
+    match uri.query() {
+      Some(query) if !to.contains('?') => Some((action, format!("{}?{}", to, query))),
+      _                                => Some((action, to)),
+    }
+  }
+}
+
+impl Handler for Rules {
+  fn handle<'r>(&self, request: &'r Request, data: Data) -> Outcome<'r> {
+    match self.target(request) {
+      Some((Action::Redirect(Status::Found), to)) => Outcome::from(request, Redirect::found(to)),
+      Some((Action::Redirect(_), to))             => Outcome::from(request, Redirect::moved(to)),
+      _                                           => Outcome::forward(data),
+    }
+  }
+}
+
+impl From<Rules> for Vec<Route> {
+  fn from(rules: Rules) -> Vec<Route> {
+    vec![Route::ranked(RANK, Method::Get, "/<path..>", rules)]
+  }
+}
+
+// Loads the rules named by `redirects_file` in Rocket.toml and puts them in
+// front of every route. Aliases are rewritten before routing even starts.
+pub struct Redirects;
+
+impl Fairing for Redirects {
+  fn info(&self) -> Info {
+    Info {
+      name: "Redirects",
+      kind: Kind::Attach | Kind::Request,
+    }
+  }
+
+  fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+    let file = rocket
+      .config()
+      .get_str("redirects_file")
+      .unwrap_or(DEFAULT_FILE)
+      .to_string();
+
+    match Rules::load(&file) {
+      Ok(rules) => Ok(rocket.manage(rules.clone()).mount("/", rules)),
+      Err(err)  => {
+        eprintln!("Invalid redirects in {}: {}", file, err);
+        Err(rocket)
+      },
+    }
+  }
+
+  fn on_request(&self, request: &mut Request, _data: &Data) {
+    let target = request
+      .guard::<State<Rules>>()
+      .succeeded()
+      .and_then(|rules| rules.target(request));
+
+    if let Some((Action::Alias, to)) = target {
+      if let Ok(uri) = Origin::parse_owned(to) {
+        request.set_uri(uri);
+      }
+    }
+  }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Segment>, String> {
+  let segments = pattern
+    .trim_start_matches('/')
+    .split('/')
+    .map(|segment| if segment.starts_with(':') && segment.len() > 1 {
+      Segment::Param(segment.to_string())
+    } else if segment.starts_with('*') && segment.len() > 1 {
+      Segment::Rest(segment.to_string())
+    } else {
+      Segment::Literal(segment.to_string())
+    })
+    .collect::<Vec<Segment>>();
+
+  let rest = segments.iter().position(|segment| matches!(segment, Segment::Rest(_)));
+  match rest {
+    Some(index) if index + 1 != segments.len() => Err(format!("`*` may only end a pattern, as in `{}`", pattern)),
+    _                                          => Ok(segments),
+  }
+}
+
+fn match_pattern(segments: &[Segment], path: &str) -> Option<Vec<(String, String)>> {
+  let parts = path.trim_start_matches('/').split('/').collect::<Vec<&str>>();
+  let mut captures = Vec::new();
+
+  for (index, segment) in segments.iter().enumerate() {
+    match segment {
+      Segment::Literal(literal) => if parts.get(index) != Some(&literal.as_str()) {
+        return None;
+      },
+      Segment::Param(name)      => match parts.get(index) {
+        Some(part) if !part.is_empty() => captures.push((name.clone(), part.to_string())),
+        _                              => return None,
+      },
+      Segment::Rest(name)       => {
+        captures.push((name.clone(), parts[index.min(parts.len())..].join("/")));
+        return Some(captures);
+      },
+    }
+  }
+
+  if parts.len() == segments.len() {
+    Some(captures)
+  } else {
+    None
+  }
+}
+
+fn placeholder_name(segment: &Segment) -> Option<String> {
+  match segment {
+    Segment::Param(name) | Segment::Rest(name) => Some(name.clone()),
+    Segment::Literal(_)                        => None,
+  }
+}
+
+// `:name` and `*name` placeholders used in a target.
+fn placeholders(target: &str) -> Vec<String> {
+  let path = target
+    .splitn(2, "://")
+    .last()
+    .unwrap_or(target);
+
+  path
+    .split(&['/', '?', '&', '='][..])
+    .filter(|part| (part.starts_with(':') || part.starts_with('*')) && part.len() > 1)
+    .map(str::to_string)
+    .collect()
+}
+
+fn strip_query(url: &str) -> &str {
+  url.split('?').next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rule(kind: MatchKind, from: &str, to: &str) -> Rule {
+    Rule::from_config(RuleConfig {
+      from: from.to_string(),
+      to: to.to_string(),
+      kind: Some(kind),
+      status: None,
+      alias: false,
+    }).unwrap()
+  }
+
+  // Loading turns these rules away as cycles, but the targets they build
+  // have to stay on this host regardless.
+  #[test]
+  fn targets_stay_on_this_host() {
+    let prefix = rule(MatchKind::Prefix, "/old", "/");
+    assert_eq!(prefix.apply("/old//evil.com"), Some("/evil.com".to_string()));
+    assert_eq!(prefix.apply("/old/\\evil.com"), Some("/evil.com".to_string()));
+    assert_eq!(prefix.apply("/old/about"), Some("/about".to_string()));
+
+    let pattern = rule(MatchKind::Pattern, "/docs/*rest", "/*rest");
+    assert_eq!(pattern.apply("/docs//evil.com"), Some("/evil.com".to_string()));
+
+    let external = rule(MatchKind::Prefix, "/gh", "https://github.com/arcrose");
+    assert_eq!(external.apply("/gh/clink"), Some("https://github.com/arcrose/clink".to_string()));
+  }
+}
@@ -0,0 +1,634 @@
+pub mod pdf;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rocket::{Outcome, Request, State};
+use rocket::http::{Accept, ContentType};
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder};
+use rocket::response::content::{Content, Json};
+use serde_json::{self, Value};
+
+use compression;
+use fingerprint::Manifest;
+use i18n::{ByLocale, Locale};
+use pages;
+use templates::Templates;
+
+
+// Plain text is wrapped to fit a terminal or an email.
+const TEXT_WIDTH: usize = 78;
+
+const MONTHS: [&str; 12] = [
+  "Jan.", "Feb.", "Mar.", "Apr.", "May", "June", "July", "Aug.", "Sept.", "Oct.", "Nov.", "Dec.",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+  Html,
+  Text,
+  Markdown,
+  Json,
+  Pdf,
+}
+
+// In order of preference when a request accepts several equally.
+const FORMATS: [Format; 5] = [Format::Html, Format::Text, Format::Markdown, Format::Json, Format::Pdf];
+
+impl Format {
+  pub fn content_type(self) -> ContentType {
+    match self {
+      Format::Html     => ContentType::HTML,
+      Format::Text     => ContentType::Plain,
+      Format::Markdown => ContentType::with_params("text", "markdown", ("charset", "utf-8")),
+      Format::Json     => ContentType::JSON,
+      Format::Pdf      => ContentType::PDF,
+    }
+  }
+
+  fn media_types(self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Format::Html     => &[("text", "html"), ("application", "xhtml+xml")],
+      Format::Text     => &[("text", "plain")],
+      Format::Markdown => &[("text", "markdown"), ("text", "x-markdown")],
+      Format::Json     => &[("application", "json")],
+      Format::Pdf      => &[("application", "pdf")],
+    }
+  }
+
+  // The format a request's `Accept` header weighs highest. Anything that
+  // doesn't say, or accepts none of them, gets HTML.
+  pub fn negotiate(request: &Request) -> Format {
+    let accept = match request.accept() {
+      Some(accept) => accept,
+      None         => return Format::Html,
+    };
+    let mut best = (Format::Html, 0.0);
+
+    for &format in &FORMATS {
+      let quality = quality(accept, format);
+      if quality > best.1 {
+        best = (format, quality);
+      }
+    }
+
+    best.0
+  }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Format {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Format, ()> {
+    Outcome::Success(Format::negotiate(request))
+  }
+}
+
+// The resume rendered as a PDF, which is done once at startup since laying it
+// out takes a while.
+pub struct Pdf(pub Vec<u8>);
+
+// A response chosen by the `Accept` header, which caches have to key on.
+pub struct Negotiated<R>(pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for Negotiated<R> {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = self.0.respond_to(request)?;
+    compression::add_vary(&mut response, "Accept");
+    Ok(response)
+  }
+}
+
+#[get("/resume")]
+pub fn page(
+  format: Format,
+  locale: Locale,
+  resume: State<Resume>,
+  pdf: State<Pdf>,
+  templates: State<Templates>,
+  manifest: State<Manifest>,
+) -> Result<ByLocale<Negotiated<Content<Vec<u8>>>>, io::Error> {
+  let body = match format {
+    Format::Html     => manifest.rewrite_html(&pages::resume_page(&templates, &resume, &locale)?).into_bytes(),
+    Format::Text     => resume.to_text().into_bytes(),
+    Format::Markdown => resume.to_markdown().into_bytes(),
+    Format::Json     => resume.to_json().as_bytes().to_vec(),
+    Format::Pdf      => pdf.0.clone(),
+  };
+
+  Ok(ByLocale(Negotiated(Content(format.content_type(), body))))
+}
+
+#[get("/resume.txt")]
+pub fn text(resume: State<Resume>) -> Content<String> {
+  Content(Format::Text.content_type(), resume.to_text())
+}
+
+#[get("/resume.md")]
+pub fn markdown(resume: State<Resume>) -> Content<String> {
+  Content(Format::Markdown.content_type(), resume.to_markdown())
+}
+
+#[get("/resume.json")]
+pub fn json(resume: State<Resume>) -> Json<String> {
+  Json(resume.to_json().to_string())
+}
+
+#[get("/resume.pdf")]
+pub fn pdf(pdf: State<Pdf>) -> Content<Vec<u8>> {
+  Content(ContentType::PDF, pdf.0.clone())
+}
+
+// A resume in the JSON Resume schema, https://jsonresume.org/schema/. Only
+// the sections shown on the site are read, but the JSON export keeps the
+// whole file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Resume {
+  pub basics: Basics,
+  #[serde(default)]
+  pub work: Vec<Work>,
+  #[serde(default)]
+  pub volunteer: Vec<Volunteer>,
+  #[serde(default)]
+  pub education: Vec<Education>,
+  #[serde(default)]
+  pub projects: Vec<Project>,
+  #[serde(default)]
+  pub skills: Vec<Skill>,
+  #[serde(default)]
+  pub meta: Meta,
+  // From `meta.lastModified`, or when the file was last modified.
+  #[serde(skip)]
+  pub updated: Option<DateTime<Utc>>,
+  #[serde(skip)]
+  json: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+  pub last_modified: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Basics {
+  pub name: String,
+  pub label: Option<String>,
+  pub email: Option<String>,
+  pub url: Option<String>,
+  pub summary: Option<String>,
+  #[serde(default)]
+  pub profiles: Vec<Profile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Profile {
+  pub network: String,
+  pub username: Option<String>,
+  pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Work {
+  pub name: String,
+  pub position: String,
+  pub url: Option<String>,
+  pub start_date: Option<String>,
+  pub end_date: Option<String>,
+  pub summary: Option<String>,
+  #[serde(default)]
+  pub highlights: Vec<String>,
+  // Not part of the schema, which allows extra fields, but projects have it.
+  #[serde(default)]
+  pub keywords: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volunteer {
+  pub organization: String,
+  pub position: String,
+  pub url: Option<String>,
+  pub start_date: Option<String>,
+  pub end_date: Option<String>,
+  pub summary: Option<String>,
+  #[serde(default)]
+  pub highlights: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Education {
+  pub institution: String,
+  pub url: Option<String>,
+  pub area: Option<String>,
+  pub study_type: Option<String>,
+  pub start_date: Option<String>,
+  pub end_date: Option<String>,
+  pub score: Option<String>,
+  #[serde(default)]
+  pub courses: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+  pub name: String,
+  pub description: Option<String>,
+  pub url: Option<String>,
+  pub start_date: Option<String>,
+  pub end_date: Option<String>,
+  #[serde(default)]
+  pub highlights: Vec<String>,
+  #[serde(default)]
+  pub keywords: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Skill {
+  pub name: String,
+  pub level: Option<String>,
+  #[serde(default)]
+  pub keywords: Vec<String>,
+}
+
+// The parts of a resume every format shows, in the order they're shown.
+#[derive(Debug, Serialize)]
+pub struct Link {
+  pub label: String,
+  pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Section {
+  pub heading: &'static str,
+  pub subheading: &'static str,
+  pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+  pub title: String,
+  pub url: Option<String>,
+  pub dates: String,
+  pub keywords: Vec<String>,
+  pub summary: Option<String>,
+  pub highlights: Vec<String>,
+}
+
+impl Resume {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Resume, io::Error> {
+    let mut resume = Resume::parse(&fs::read_to_string(&path)?)
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if resume.updated.is_none() {
+      resume.updated = Some(DateTime::from(fs::metadata(&path)?.modified()?));
+    }
+
+    Ok(resume)
+  }
+
+  pub fn parse(content: &str) -> Result<Resume, String> {
+    let value = serde_json::from_str::<Value>(content).map_err(|err| err.to_string())?;
+    let mut resume = serde_json::from_value::<Resume>(value.clone()).map_err(|err| err.to_string())?;
+    resume.check_dates()?;
+    if let Some(ref modified) = resume.meta.last_modified {
+      let updated = DateTime::parse_from_rfc3339(modified)
+        .map(|updated| updated.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(modified, "%Y-%m-%dT%H:%M:%S").map(|updated| Utc.from_utc_datetime(&updated)))
+        .map_err(|_| format!("meta.lastModified `{}` is not a date and time like 2017-12-24T15:53:00", modified))?;
+      resume.updated = Some(updated);
+    }
+    resume.json = serde_json::to_string_pretty(&value).map_err(|err| err.to_string())?;
+
+    Ok(resume)
+  }
+
+  // The whole resume as it was loaded, including sections the site doesn't
+  // show.
+  pub fn to_json(&self) -> &str {
+    &self.json
+  }
+
+  pub fn links(&self) -> Vec<Link> {
+    let basics = &self.basics;
+    let mut links = basics.profiles
+      .iter()
+      .filter_map(|profile| profile.url.as_ref().map(|url| Link {
+        label: profile.network.clone(),
+        url: url.clone(),
+      }))
+      .collect::<Vec<Link>>();
+
+    if let Some(ref url) = basics.url {
+      links.push(Link { label: "Personal website".to_string(), url: url.clone() });
+    }
+    if let Some(ref email) = basics.email {
+      links.push(Link { label: "Email".to_string(), url: format!("mailto:{}", email) });
+    }
+
+    links
+  }
+
+  // Sections with nothing in them are left out.
+  pub fn sections(&self) -> Vec<Section> {
+    let education = self.education
+      .iter()
+      .map(|education| {
+        let study = vec![&education.study_type, &education.area]
+          .into_iter()
+          .filter_map(|part| part.as_ref().map(String::as_str))
+          .collect::<Vec<&str>>()
+          .join(" ");
+        Entry {
+          title: if study.is_empty() {
+            education.institution.clone()
+          } else {
+            format!("{} - {}", study, education.institution)
+          },
+          url: education.url.clone(),
+          dates: period(&education.start_date, &education.end_date),
+          keywords: education.courses.clone(),
+          summary: education.score.as_ref().map(|score| format!("Score: {}", score)),
+          highlights: Vec::new(),
+        }
+      })
+      .collect();
+
+    let work = self.work
+      .iter()
+      .map(|work| Entry {
+        title: format!("{} - {}", work.position, work.name),
+        url: work.url.clone(),
+        dates: period(&work.start_date, &work.end_date),
+        keywords: work.keywords.clone(),
+        summary: work.summary.clone(),
+        highlights: work.highlights.clone(),
+      })
+      .collect();
+
+    let projects = self.projects
+      .iter()
+      .map(|project| Entry {
+        title: project.name.clone(),
+        url: project.url.clone(),
+        dates: period(&project.start_date, &project.end_date),
+        keywords: project.keywords.clone(),
+        summary: project.description.clone(),
+        highlights: project.highlights.clone(),
+      })
+      .chain(self.volunteer.iter().map(|volunteer| Entry {
+        title: format!("{} - {}", volunteer.position, volunteer.organization),
+        url: volunteer.url.clone(),
+        dates: period(&volunteer.start_date, &volunteer.end_date),
+        keywords: Vec::new(),
+        summary: volunteer.summary.clone(),
+        highlights: volunteer.highlights.clone(),
+      }))
+      .collect();
+
+    let skills = self.skills
+      .iter()
+      .map(|skill| Entry {
+        title: match skill.level {
+          Some(ref level) => format!("{} ({})", skill.name, level),
+          None            => skill.name.clone(),
+        },
+        url: None,
+        dates: String::new(),
+        keywords: skill.keywords.clone(),
+        summary: None,
+        highlights: Vec::new(),
+      })
+      .collect();
+
+    vec![
+      Section { heading: "Education", subheading: "", entries: education },
+      Section { heading: "Experience", subheading: "in Industry", entries: work },
+      Section { heading: "Projects", subheading: "and Volunteering", entries: projects },
+      Section { heading: "Skills", subheading: "", entries: skills },
+    ]
+      .into_iter()
+      .filter(|section| !section.entries.is_empty())
+      .collect()
+  }
+
+  pub fn to_text(&self) -> String {
+    let basics = &self.basics;
+    let mut text = format!("{}\n", basics.name.to_uppercase());
+    if let Some(ref label) = basics.label {
+      text.push_str(&format!("{}\n", label));
+    }
+    for link in self.links() {
+      text.push_str(&format!("{}: {}\n", link.label, link.url.trim_start_matches("mailto:")));
+    }
+    if let Some(ref summary) = basics.summary {
+      text.push_str(&format!("\n{}", wrap(summary, "", "")));
+    }
+
+    for section in self.sections() {
+      text.push_str(&format!("\n\n{}\n", heading(&section).to_uppercase()));
+
+      for entry in section.entries {
+        text.push_str(&format!("\n{}\n", entry.title));
+        if let Some(ref url) = entry.url {
+          text.push_str(&format!("{}\n", url));
+        }
+        if !entry.dates.is_empty() {
+          text.push_str(&format!("{}\n", entry.dates));
+        }
+        if !entry.keywords.is_empty() {
+          text.push_str(&wrap(&entry.keywords.join(", "), "", ""));
+        }
+        if let Some(ref summary) = entry.summary {
+          text.push_str(&wrap(summary, "", ""));
+        }
+        for highlight in &entry.highlights {
+          text.push_str(&wrap(highlight, "  * ", "    "));
+        }
+      }
+    }
+
+    text
+  }
+
+  pub fn to_markdown(&self) -> String {
+    let basics = &self.basics;
+    let mut markdown = format!("# {}\n", escape_markdown(&basics.name));
+    if let Some(ref label) = basics.label {
+      markdown.push_str(&format!("\n*{}*\n", escape_markdown(label)));
+    }
+
+    let links = self.links();
+    if !links.is_empty() {
+      markdown.push('\n');
+    }
+    for link in links {
+      markdown.push_str(&format!("- {}: <{}>\n", escape_markdown(&link.label), link.url));
+    }
+    if let Some(ref summary) = basics.summary {
+      markdown.push_str(&format!("\n{}\n", escape_markdown(summary)));
+    }
+
+    for section in self.sections() {
+      markdown.push_str(&format!("\n## {}\n", heading(&section)));
+
+      for entry in section.entries {
+        match entry.url {
+          Some(ref url) => markdown.push_str(&format!("\n### [{}]({})\n", escape_markdown(&entry.title), url)),
+          None          => markdown.push_str(&format!("\n### {}\n", escape_markdown(&entry.title))),
+        }
+        if !entry.dates.is_empty() {
+          markdown.push_str(&format!("\n*{}*\n", entry.dates));
+        }
+        if !entry.keywords.is_empty() {
+          markdown.push_str(&format!("\n{}\n", escape_markdown(&entry.keywords.join(", "))));
+        }
+        if let Some(ref summary) = entry.summary {
+          markdown.push_str(&format!("\n{}\n", escape_markdown(summary)));
+        }
+        if !entry.highlights.is_empty() {
+          markdown.push('\n');
+        }
+        for highlight in &entry.highlights {
+          markdown.push_str(&format!("- {}\n", escape_markdown(highlight)));
+        }
+      }
+    }
+
+    markdown
+  }
+
+  // Every date has to be one the schema allows, and no period can end
+  // before it starts.
+  fn check_dates(&self) -> Result<(), String> {
+    let periods = self.work
+      .iter()
+      .map(|work| (&work.start_date, &work.end_date))
+      .chain(self.volunteer.iter().map(|volunteer| (&volunteer.start_date, &volunteer.end_date)))
+      .chain(self.education.iter().map(|education| (&education.start_date, &education.end_date)))
+      .chain(self.projects.iter().map(|project| (&project.start_date, &project.end_date)));
+    let mut problems = Vec::new();
+
+    for (start, end) in periods {
+      for date in start.iter().chain(end.iter()) {
+        if format_date(date).is_none() {
+          problems.push(format!("`{}` is not a date like 2015, 2015-12 or 2015-12-31", date));
+        }
+      }
+      if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+          problems.push(format!("{} - {} ends before it starts", start, end));
+        }
+      }
+    }
+
+    if problems.is_empty() {
+      Ok(())
+    } else {
+      Err(problems.join("; "))
+    }
+  }
+}
+
+// How well a request accepts a format: the weight of the most specific media
+// range that matches it, or 0.
+fn quality(accept: &Accept, format: Format) -> f32 {
+  accept
+    .iter()
+    .filter_map(|item| {
+      let media_type = item.media_type();
+      let specificity = format
+        .media_types()
+        .iter()
+        .filter_map(|&(top, sub)| match (media_type.top() == top, media_type.sub() == sub) {
+          (true, true)                                             => Some(2),
+          (true, false) if media_type.sub() == "*"                 => Some(1),
+          _ if media_type.top() == "*" && media_type.sub() == "*"  => Some(0),
+          _                                                        => None,
+        })
+        .max()?;
+      Some((specificity, item.weight_or(1.0)))
+    })
+    .max_by_key(|&(specificity, _)| specificity)
+    .map(|(_, quality)| quality)
+    .unwrap_or(0.0)
+}
+
+// Dates in the schema's ISO 8601 forms, `2015`, `2015-12` or `2015-12-31`,
+// are shown as `2015` or `Dec. 2015`.
+pub fn format_date(date: &str) -> Option<String> {
+  let parts = date.split('-').collect::<Vec<&str>>();
+  let year = parts[0];
+  if year.len() != 4 || year.parse::<i32>().is_err() {
+    return None;
+  }
+
+  match parts[1..] {
+    []           => Some(year.to_string()),
+    [month]      => {
+      let month = month.parse::<usize>().ok().filter(|month| (1..=12).contains(month))?;
+      Some(format!("{} {}", MONTHS[month - 1], year))
+    },
+    [month, day] => {
+      let date = NaiveDate::parse_from_str(&format!("{}-{}-{}", year, month, day), "%Y-%m-%d").ok()?;
+      Some(format!("{} {}", MONTHS[date.month0() as usize], year))
+    },
+    _            => None,
+  }
+}
+
+fn period(start: &Option<String>, end: &Option<String>) -> String {
+  let show = |date: &String| format_date(date).unwrap_or_else(|| date.clone());
+
+  match (start, end) {
+    (Some(start), Some(end)) => format!("{} - {}", show(start), show(end)),
+    (Some(start), None)      => format!("{} - Present", show(start)),
+    (None, Some(end))        => show(end),
+    (None, None)             => String::new(),
+  }
+}
+
+fn heading(section: &Section) -> String {
+  format!("{} {}", section.heading, section.subheading).trim().to_string()
+}
+
+// Word wrap a paragraph, starting its first line with `first` and the rest
+// with `rest`.
+fn wrap(text: &str, first: &str, rest: &str) -> String {
+  let mut wrapped = String::new();
+  let mut line = first.to_string();
+  let mut empty = true;
+
+  for word in text.split_whitespace() {
+    if !empty && line.chars().count() + 1 + word.chars().count() > TEXT_WIDTH {
+      wrapped.push_str(&line);
+      wrapped.push('\n');
+      line = rest.to_string();
+      empty = true;
+    }
+    if !empty {
+      line.push(' ');
+    }
+    line.push_str(word);
+    empty = false;
+  }
+  wrapped.push_str(&line);
+  wrapped.push('\n');
+
+  wrapped
+}
+
+fn escape_markdown(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if "\\`*_[]<>#|".contains(c) {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
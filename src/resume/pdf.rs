@@ -0,0 +1,476 @@
+use std::io;
+
+use printpdf::{
+  Actions,
+  BorderArray,
+  Color,
+  IndirectFontRef,
+  LinkAnnotation,
+  Mm,
+  PdfDocument,
+  PdfDocumentReference,
+  PdfLayerReference,
+  Pt,
+  Rect,
+  Rgb,
+};
+use ttf_parser::Face;
+
+use resume::{Entry, Resume, Section};
+
+
+// Fonts are compiled in so that rendering needs nothing from the system.
+const REGULAR_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans-Bold.ttf");
+
+// US Letter, in points.
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+const LEADING: f32 = 1.35;
+
+const TEXT: (f32, f32, f32) = (0.2, 0.2, 0.2);
+const MUTED: (f32, f32, f32) = (0.4, 0.4, 0.4);
+const ACCENT: (f32, f32, f32) = (1.0, 0.2, 0.4);
+const LINK: (f32, f32, f32) = (0.0, 0.4, 1.0);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Weight {
+  Regular,
+  Bold,
+}
+
+// A font's metrics, for measuring text before it's placed.
+struct Metrics<'a> {
+  face: Face<'a>,
+}
+
+impl<'a> Metrics<'a> {
+  fn new(data: &'a [u8]) -> Result<Metrics<'a>, io::Error> {
+    let face = Face::parse(data, 0)
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    Ok(Metrics { face })
+  }
+
+  fn width(&self, text: &str, size: f32) -> f32 {
+    let units = text
+      .chars()
+      .filter_map(|c| self.face.glyph_index(c))
+      .filter_map(|glyph| self.face.glyph_hor_advance(glyph))
+      .map(f32::from)
+      .sum::<f32>();
+    units * size / f32::from(self.face.units_per_em())
+  }
+
+  fn ascent(&self, size: f32) -> f32 {
+    f32::from(self.face.ascender()) * size / f32::from(self.face.units_per_em())
+  }
+
+  fn descent(&self, size: f32) -> f32 {
+    -f32::from(self.face.descender()) * size / f32::from(self.face.units_per_em())
+  }
+}
+
+struct Fonts<'a> {
+  regular: Metrics<'a>,
+  bold: Metrics<'a>,
+}
+
+impl Fonts<'static> {
+  fn load() -> Result<Fonts<'static>, io::Error> {
+    Ok(Fonts {
+      regular: Metrics::new(REGULAR_FONT)?,
+      bold: Metrics::new(BOLD_FONT)?,
+    })
+  }
+}
+
+impl<'a> Fonts<'a> {
+  fn get(&self, weight: Weight) -> &Metrics<'a> {
+    match weight {
+      Weight::Regular => &self.regular,
+      Weight::Bold    => &self.bold,
+    }
+  }
+}
+
+// A piece of text on a line, `x` points from the left margin.
+struct Run {
+  text: String,
+  x: f32,
+  weight: Weight,
+  size: f32,
+  color: (f32, f32, f32),
+  link: Option<String>,
+}
+
+struct Line {
+  runs: Vec<Run>,
+  size: f32,
+  space_before: f32,
+}
+
+impl Line {
+  fn new(size: f32, space_before: f32) -> Line {
+    Line { runs: Vec::new(), size, space_before }
+  }
+
+  fn height(&self) -> f32 {
+    self.space_before + self.size * LEADING
+  }
+}
+
+// Lines that are kept on one page, like a whole entry, unless they couldn't
+// fit on any page.
+struct Block {
+  lines: Vec<Line>,
+}
+
+impl Block {
+  fn height(&self) -> f32 {
+    self.lines.iter().map(Line::height).sum()
+  }
+}
+
+// Lay a resume out on as many pages as it needs, with links in it clickable.
+pub fn render(resume: &Resume) -> Result<Vec<u8>, io::Error> {
+  let fonts = Fonts::load()?;
+  let blocks = layout(resume, &fonts);
+
+  let title = format!("Résumé - {}", resume.basics.name);
+  let (document, page, layer) = PdfDocument::new(title, mm(PAGE_WIDTH), mm(PAGE_HEIGHT), "Resume");
+  let document = document
+    .with_author(resume.basics.name.clone())
+    .with_creator("homupage");
+  let regular = document.add_external_font(REGULAR_FONT).map_err(to_io_error)?;
+  let bold = document.add_external_font(BOLD_FONT).map_err(to_io_error)?;
+
+  let mut layers = vec![document.get_page(page).get_layer(layer)];
+  for (block, placed) in blocks.iter().zip(place(&blocks, &fonts)) {
+    for (line, (page, baseline)) in block.lines.iter().zip(placed) {
+      while layers.len() <= page {
+        layers.push(new_page(&document));
+      }
+      for run in &line.runs {
+        let font = match run.weight {
+          Weight::Regular => &regular,
+          Weight::Bold    => &bold,
+        };
+        draw_run(&layers[page], &fonts, font, run, baseline);
+      }
+    }
+  }
+
+  document.save_to_bytes().map_err(to_io_error)
+}
+
+// Where each line of each block goes: the page it's on and its baseline. A
+// block that doesn't fit in what's left of a page starts the next one.
+fn place(blocks: &[Block], fonts: &Fonts) -> Vec<Vec<(usize, f32)>> {
+  let mut page = 0;
+  let mut top = PAGE_HEIGHT - MARGIN;
+  let mut page_empty = true;
+  let mut placed = Vec::new();
+
+  for block in blocks {
+    if top - block.height() < MARGIN && !page_empty {
+      page += 1;
+      top = PAGE_HEIGHT - MARGIN;
+      page_empty = true;
+    }
+
+    let mut lines = Vec::new();
+    for line in &block.lines {
+      // Blocks taller than a page break between lines instead.
+      if top - line.height() < MARGIN && !page_empty {
+        page += 1;
+        top = PAGE_HEIGHT - MARGIN;
+        page_empty = true;
+      }
+      if !page_empty {
+        top -= line.space_before;
+      }
+      page_empty = false;
+      lines.push((page, top - fonts.regular.ascent(line.size)));
+      top -= line.size * LEADING;
+    }
+    placed.push(lines);
+  }
+
+  placed
+}
+
+fn new_page(document: &PdfDocumentReference) -> PdfLayerReference {
+  let (page, layer) = document.add_page(mm(PAGE_WIDTH), mm(PAGE_HEIGHT), "Resume");
+  document.get_page(page).get_layer(layer)
+}
+
+fn draw_run(layer: &PdfLayerReference, fonts: &Fonts, font: &IndirectFontRef, run: &Run, baseline: f32) {
+  let (red, green, blue) = run.color;
+  let x = MARGIN + run.x;
+
+  layer.set_fill_color(Color::Rgb(Rgb::new(red, green, blue, None)));
+  layer.use_text(run.text.clone(), run.size, mm(x), mm(baseline), font);
+
+  if let Some(ref url) = run.link {
+    let metrics = fonts.get(run.weight);
+    let rect = Rect::new(
+      mm(x),
+      mm(baseline - metrics.descent(run.size)),
+      mm(x + metrics.width(&run.text, run.size)),
+      mm(baseline + metrics.ascent(run.size)));
+    layer.add_link_annotation(LinkAnnotation::new(
+      rect,
+      Some(BorderArray::Solid([0.0, 0.0, 0.0])),
+      None,
+      Actions::uri(url.clone()),
+      None));
+  }
+}
+
+fn layout(resume: &Resume, fonts: &Fonts) -> Vec<Block> {
+  let width = PAGE_WIDTH - 2.0 * MARGIN;
+  let basics = &resume.basics;
+  let mut header = Vec::new();
+
+  header.push(centered(fonts, &basics.name, Weight::Bold, 20.0, TEXT, width));
+  if let Some(ref label) = basics.label {
+    header.push(centered(fonts, label, Weight::Regular, 11.0, MUTED, width));
+  }
+  header.extend(link_lines(resume, fonts, width));
+  if let Some(ref summary) = basics.summary {
+    header.extend(paragraph(fonts, summary, 10.0, TEXT, 0.0, "", width, 8.0));
+  }
+
+  let mut blocks = vec![Block { lines: header }];
+  for section in resume.sections() {
+    // A heading stays with the first entry under it.
+    let mut entries = section.entries.iter();
+    let mut first = vec![heading(fonts, &section)];
+    if let Some(entry) = entries.next() {
+      first.extend(entry_lines(fonts, entry, width));
+    }
+    blocks.push(Block { lines: first });
+
+    for entry in entries {
+      blocks.push(Block { lines: entry_lines(fonts, entry, width) });
+    }
+  }
+
+  blocks
+}
+
+// `Label: url` for each link, wrapped and centered, with the URLs clickable.
+fn link_lines(resume: &Resume, fonts: &Fonts, width: f32) -> Vec<Line> {
+  let size = 9.0;
+  let gap = fonts.regular.width("    ", size);
+  let mut lines = Vec::new();
+  let mut line = Line::new(size, 6.0);
+  let mut x = 0.0;
+
+  for link in resume.links() {
+    let label = format!("{}: ", link.label);
+    let shown = link.url.trim_start_matches("mailto:").to_string();
+    let label_width = fonts.regular.width(&label, size);
+    let url_width = fonts.regular.width(&shown, size);
+
+    if !line.runs.is_empty() && x + gap + label_width + url_width > width {
+      lines.push(line);
+      line = Line::new(size, 0.0);
+      x = 0.0;
+    }
+    if !line.runs.is_empty() {
+      x += gap;
+    }
+    line.runs.push(Run { text: label, x, weight: Weight::Regular, size, color: TEXT, link: None });
+    x += label_width;
+    line.runs.push(Run { text: shown, x, weight: Weight::Regular, size, color: LINK, link: Some(link.url) });
+    x += url_width;
+  }
+  if !line.runs.is_empty() {
+    lines.push(line);
+  }
+
+  for line in &mut lines {
+    let used = line.runs
+      .last()
+      .map(|run| run.x + fonts.regular.width(&run.text, size))
+      .unwrap_or(0.0);
+    let offset = (width - used) / 2.0;
+    for run in &mut line.runs {
+      run.x += offset;
+    }
+  }
+
+  lines
+}
+
+fn heading(fonts: &Fonts, section: &Section) -> Line {
+  let size = 14.0;
+  let mut line = Line::new(size, 18.0);
+  line.runs.push(Run {
+    text: section.heading.to_string(),
+    x: 0.0,
+    weight: Weight::Bold,
+    size,
+    color: ACCENT,
+    link: None,
+  });
+  if !section.subheading.is_empty() {
+    let x = fonts.bold.width(&format!("{} ", section.heading), size);
+    line.runs.push(Run {
+      text: section.subheading.to_string(),
+      x,
+      weight: Weight::Bold,
+      size,
+      color: TEXT,
+      link: None,
+    });
+  }
+
+  line
+}
+
+fn entry_lines(fonts: &Fonts, entry: &Entry, width: f32) -> Vec<Line> {
+  let title_size = 11.0;
+  let date_size = 9.0;
+  let date_width = fonts.regular.width(&entry.dates, date_size);
+  let title_width = width - date_width - if entry.dates.is_empty() { 0.0 } else { 12.0 };
+  let color = if entry.url.is_some() { LINK } else { TEXT };
+
+  let mut lines = wrap(fonts.get(Weight::Bold), &entry.title, title_size, title_width)
+    .into_iter()
+    .enumerate()
+    .map(|(index, text)| {
+      let mut line = Line::new(title_size, if index == 0 { 10.0 } else { 0.0 });
+      line.runs.push(Run { text, x: 0.0, weight: Weight::Bold, size: title_size, color, link: entry.url.clone() });
+      line
+    })
+    .collect::<Vec<Line>>();
+  if !entry.dates.is_empty() {
+    lines[0].runs.push(Run {
+      text: entry.dates.clone(),
+      x: width - date_width,
+      weight: Weight::Regular,
+      size: date_size,
+      color: MUTED,
+      link: None,
+    });
+  }
+
+  if !entry.keywords.is_empty() {
+    lines.extend(paragraph(fonts, &entry.keywords.join(", "), 9.0, MUTED, 0.0, "", width, 2.0));
+  }
+  if let Some(ref summary) = entry.summary {
+    lines.extend(paragraph(fonts, summary, 10.0, TEXT, 0.0, "", width, 2.0));
+  }
+  for (index, highlight) in entry.highlights.iter().enumerate() {
+    let space_before = if index == 0 { 4.0 } else { 1.0 };
+    lines.extend(paragraph(fonts, highlight, 10.0, TEXT, 12.0, "•", width, space_before));
+  }
+
+  lines
+}
+
+fn centered(fonts: &Fonts, text: &str, weight: Weight, size: f32, color: (f32, f32, f32), width: f32) -> Line {
+  let mut line = Line::new(size, 0.0);
+  let x = ((width - fonts.get(weight).width(text, size)) / 2.0).max(0.0);
+  line.runs.push(Run { text: text.to_string(), x, weight, size, color, link: None });
+  line
+}
+
+// A wrapped paragraph indented by `indent`, with `bullet` hanging in the
+// indent of its first line.
+#[allow(clippy::too_many_arguments)]
+fn paragraph(
+  fonts: &Fonts,
+  text: &str,
+  size: f32,
+  color: (f32, f32, f32),
+  indent: f32,
+  bullet: &str,
+  width: f32,
+  space_before: f32,
+) -> Vec<Line> {
+  wrap(&fonts.regular, text, size, width - indent)
+    .into_iter()
+    .enumerate()
+    .map(|(index, text)| {
+      let mut line = Line::new(size, if index == 0 { space_before } else { 0.0 });
+      if index == 0 && !bullet.is_empty() {
+        line.runs.push(Run { text: bullet.to_string(), x: indent / 3.0, weight: Weight::Regular, size, color, link: None });
+      }
+      line.runs.push(Run { text, x: indent, weight: Weight::Regular, size, color, link: None });
+      line
+    })
+    .collect()
+}
+
+fn wrap(metrics: &Metrics, text: &str, size: f32, width: f32) -> Vec<String> {
+  let mut lines = Vec::new();
+  let mut line = String::new();
+
+  for word in text.split_whitespace() {
+    let candidate = if line.is_empty() {
+      word.to_string()
+    } else {
+      format!("{} {}", line, word)
+    };
+    if !line.is_empty() && metrics.width(&candidate, size) > width {
+      lines.push(line);
+      line = word.to_string();
+    } else {
+      line = candidate;
+    }
+  }
+  if !line.is_empty() || lines.is_empty() {
+    lines.push(line);
+  }
+
+  lines
+}
+
+fn mm(points: f32) -> Mm {
+  Mm::from(Pt(points))
+}
+
+fn to_io_error(err: printpdf::Error) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn entries_are_not_split_between_pages() {
+    let highlights = ["\"Wrote a great deal of software, and then wrote some more of it.\""; 8].join(", ");
+    let work = (0..12)
+      .map(|n| format!(
+        r#"{{"name": "Company {}", "position": "Developer", "startDate": "2010", "highlights": [{}]}}"#,
+        n,
+        highlights))
+      .collect::<Vec<String>>()
+      .join(", ");
+    let resume = Resume::parse(&format!(r#"{{"basics": {{"name": "A"}}, "work": [{}]}}"#, work)).unwrap();
+
+    // The header, then twelve entries of nine lines each: more than a page,
+    // but no entry is split between two.
+    let fonts = Fonts::load().unwrap();
+    let blocks = layout(&resume, &fonts);
+    let pages = place(&blocks, &fonts)
+      .into_iter()
+      .map(|placed| placed.into_iter().map(|(page, _)| page).collect::<Vec<usize>>())
+      .collect::<Vec<Vec<usize>>>();
+    assert_eq!(pages.len(), 1 + 12);
+    for (entry, lines) in pages.iter().enumerate().skip(1) {
+      assert!(lines.len() > 1);
+      assert!(lines.iter().all(|&page| page == lines[0]), "entry {} is split across pages {:?}", entry, lines);
+    }
+
+    let last_page = pages.last().unwrap()[0];
+    assert!(last_page > 0);
+    let pdf = String::from_utf8_lossy(&render(&resume).unwrap()).into_owned();
+    assert_eq!(pdf.matches("/Type/Page/").count(), last_page + 1);
+  }
+}
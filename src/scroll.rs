@@ -0,0 +1,335 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use base64;
+use rocket::http::ContentType;
+
+use assets;
+use code::{self, SourceCodeDirectory};
+use config::{Animation, SiteConfig};
+use pages::escape;
+use raster::{self, Palette, Rgb, Segments};
+use segments::{self, Text};
+use writing::{self, Sample};
+
+
+pub const USAGE: &str = "animate <file.svg|file.png> [--fps N] [--seconds N] [--lines N] [--size WIDTHxHEIGHT]";
+
+// How `animate` records the index page's scroll, from the command line.
+#[derive(Debug, PartialEq)]
+pub struct Settings {
+  pub out_file: String,
+  pub fps: u32,
+  pub seconds: u32,
+  pub window_lines: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl Settings {
+  pub fn from_args(args: &[String], animation: &Animation) -> Result<Settings, String> {
+    let mut settings = Settings {
+      out_file: String::new(),
+      fps: 10,
+      seconds: 30,
+      window_lines: animation.window_lines,
+      width: 800,
+      height: 450,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+      let mut value = || args
+        .next()
+        .ok_or_else(|| format!("{} needs a value; usage: {}", arg, USAGE));
+      match arg.as_str() {
+        "--fps"     => settings.fps = positive(arg, value()?)?,
+        "--seconds" => settings.seconds = positive(arg, value()?)?,
+        "--lines"   => settings.window_lines = positive(arg, value()?)?,
+        "--size"    => {
+          let size = value()?;
+          let (width, height) = size
+            .find('x')
+            .map(|x| (&size[..x], &size[x + 1..]))
+            .ok_or_else(|| format!("--size must look like 800x450, not {}", size))?;
+          settings.width = positive(arg, width)?;
+          settings.height = positive(arg, height)?;
+        },
+        flag if flag.starts_with("--") => return Err(format!("Unknown option {}; usage: {}", flag, USAGE)),
+        file if settings.out_file.is_empty() => settings.out_file = file.to_string(),
+        extra       => return Err(format!("Unexpected argument {}; usage: {}", extra, USAGE)),
+      }
+    }
+
+    match Path::new(&settings.out_file).extension().and_then(|extension| extension.to_str()) {
+      Some("svg") | Some("png") => Ok(settings),
+      _                         => Err(format!("The animation must be written to a .svg or .png file; usage: {}", USAGE)),
+    }
+  }
+
+  fn frame_millis(&self) -> u32 {
+    (1000 / self.fps).max(1)
+  }
+}
+
+fn positive(name: &str, value: &str) -> Result<u32, String> {
+  match value.parse::<u32>() {
+    Ok(number) if number > 0 => Ok(number),
+    _                        => Err(format!("{} must be a positive whole number, not {}", name, value)),
+  }
+}
+
+// Lines of code or writing scrolling through one segment, a line at a time,
+// as the animators in `js/main.js` scroll them. Sources and samples are taken
+// in order rather than at random, so an export is the same every time.
+struct Scroll<'a, T: 'a> {
+  texts: Vec<&'a [T]>,
+  current: usize,
+  start_line: usize,
+  window: usize,
+  buffer: Vec<Option<&'a T>>,
+}
+
+impl<'a, T> Scroll<'a, T> {
+  // The writing animator pads its window with blank lines; the code animator
+  // doesn't.
+  fn new(texts: Vec<&'a [T]>, window: usize, padded: bool) -> Scroll<'a, T> {
+    let first = texts.first().cloned().unwrap_or(&[]);
+    let start = window.min(first.len());
+    let mut buffer = first[..start].iter().map(Some).collect::<Vec<Option<&T>>>();
+    if padded {
+      buffer.resize(window, None);
+    }
+
+    Scroll {
+      texts,
+      current: 0,
+      start_line: start,
+      window,
+      buffer,
+    }
+  }
+
+  // The lines showing, by their place in the window, before scrolling on by
+  // one.
+  fn step(&mut self) -> Vec<(usize, &'a T)> {
+    let showing = self.buffer
+      .iter()
+      .enumerate()
+      .filter_map(|(number, line)| line.map(|line| (number, line)))
+      .collect();
+
+    if !self.buffer.is_empty() {
+      self.buffer.remove(0);
+      let next = self.texts.get(self.current).and_then(|text| text.get(self.start_line));
+      self.buffer.push(next);
+    }
+    self.start_line += 1;
+
+    showing
+  }
+
+  // Move on to the next source or sample once this one has scrolled by.
+  fn prepare(&mut self) {
+    let length = self.texts.get(self.current).map(|text| text.len()).unwrap_or(0);
+    if self.start_line >= length.saturating_sub(self.window) && !self.texts.is_empty() {
+      self.current = (self.current + 1) % self.texts.len();
+      self.start_line = 0;
+    }
+  }
+}
+
+// What both segments show in one frame.
+#[derive(Debug)]
+pub struct Frame {
+  pub code: Vec<Text>,
+  pub writing: Vec<Text>,
+}
+
+// The frames shown over `settings.seconds`, each with how many milliseconds
+// it's shown for.
+//
+// `js/main.js` draws the first frame at once, then on every tick of
+// `step_millis` alternates between scrolling and deciding whether to move on,
+// so each later frame lasts two ticks.
+pub fn frames(
+  settings: &Settings,
+  animation: &Animation,
+  samples: &[Sample],
+  source_dirs: &[SourceCodeDirectory],
+) -> Vec<(Frame, u32)> {
+  let animation = Animation { window_lines: settings.window_lines, ..animation.clone() };
+  let window = animation.window_lines as usize;
+  let step_millis = animation.step_millis.max(1);
+
+  let sources = source_dirs
+    .iter()
+    .flat_map(|dir| dir.source_files.iter().map(|source| &source.lines_of_code[..]))
+    .collect::<Vec<&[code::Line]>>();
+  let texts = samples
+    .iter()
+    .map(|sample| &sample.lines[..])
+    .collect::<Vec<&[writing::Line]>>();
+  let mut code = Scroll::new(sources, window, false);
+  let mut writing = Scroll::new(texts, window, true);
+
+  let mut frames: Vec<(Frame, u32)> = Vec::new();
+  let mut drawn = 0;
+  let total_millis = settings.seconds * 1000;
+  let mut millis = 0;
+  while millis < total_millis {
+    let shown = settings.frame_millis().min(total_millis - millis);
+    let tick = millis / step_millis;
+    // The first frame, and another for every odd tick since.
+    let draws = tick.div_ceil(2) + 1;
+
+    if draws == drawn {
+      if let Some(last) = frames.last_mut() {
+        last.1 += shown;
+      }
+    } else {
+      // A low frame rate can skip frames, but the scroll still has to pass
+      // through them.
+      let mut frame = None;
+      while drawn < draws {
+        if drawn > 1 {
+          code.prepare();
+          writing.prepare();
+        }
+        frame = Some(Frame {
+          code: code.step().into_iter().map(|(number, line)| segments::code_text(&animation, number, line)).collect(),
+          writing: writing.step().into_iter().map(|(number, line)| segments::writing_text(&animation, number, line)).collect(),
+        });
+        drawn += 1;
+      }
+      frames.extend(frame.map(|frame| (frame, shown)));
+    }
+    millis += shown;
+  }
+
+  frames
+}
+
+// Record the scroll to `settings.out_file`, as an animated SVG or PNG.
+pub fn export(
+  settings: &Settings,
+  config: &SiteConfig,
+  samples: &[Sample],
+  source_dirs: &[SourceCodeDirectory],
+) -> Result<(), io::Error> {
+  let frames = frames(settings, &config.animation, samples, source_dirs);
+
+  if settings.out_file.ends_with(".svg") {
+    fs::write(&settings.out_file, to_svg(settings, config, &frames)?)
+  } else {
+    let palette = Palette::from_theme(&config.themes[&config.theme.light]);
+    let segments = Segments::new(settings.width, settings.height, palette)?;
+    let canvases = frames
+      .iter()
+      .map(|(frame, millis)| (segments.draw(&frame.code, &frame.writing), *millis))
+      .collect::<Vec<_>>();
+    fs::write(&settings.out_file, raster::to_apng(&canvases)?)
+  }
+}
+
+// A self-contained SVG laid out like the index page, in the light theme, where SMIL shows each frame in turn and loops forever.
+pub fn to_svg(settings: &Settings, config: &SiteConfig, frames: &[(Frame, u32)]) -> Result<String, io::Error> {
+  let (width, height) = (settings.width as f32, settings.height as f32);
+  let segment_width = raster::SEGMENT_WIDTH * width;
+  let duration = frames.iter().map(|(_, millis)| millis).sum::<u32>().max(1);
+  let theme = &config.themes[&config.theme.light];
+  let palette = Palette::from_theme(theme);
+
+  let mut svg = format!(
+    r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">
+  <style>
+{font_faces}    .code {{ font-family: "{mono_font}", monospace; font-size: {size}px; fill: {code_text}; }}
+    .writing {{ font-family: "{body_font}", sans-serif; font-size: {size}px; fill: {writing_text}; }}
+  </style>
+  <rect width="{width}" height="{height}" fill="{background}" />
+"#,
+    width = settings.width,
+    height = settings.height,
+    font_faces = font_faces(config, &[&theme.mono_font, &theme.body_font])?,
+    mono_font = theme.mono_font,
+    body_font = theme.body_font,
+    size = raster::SEGMENT_FONT_SIZE,
+    code_text = hex(palette.code_text),
+    writing_text = hex(palette.writing_text),
+    background = hex(palette.background));
+
+  let segments: [(&str, f32, &str, Rgb, Lines); 2] = [
+    ("code", 0.0, "0,0 100,0 0,100", palette.code_fill, |frame| &frame.code),
+    ("writing", width - segment_width, "100,100 100,0 0,100", palette.writing_fill, |frame| &frame.writing),
+  ];
+  for (class, x, points, fill, lines) in segments.iter() {
+    svg.push_str(&format!(
+      r#"  <svg x="{x}" width="{segment_width}" height="{height}" viewBox="0 0 100 100" preserveAspectRatio="none">
+    <clipPath id="{class}-segment"><polygon points="{points}" /></clipPath>
+    <polygon points="{points}" fill="{fill}" />
+    <g clip-path="url(#{class}-segment)">
+"#,
+      x = x,
+      segment_width = segment_width,
+      height = height,
+      class = class,
+      points = points,
+      fill = hex(*fill)));
+
+    let mut start = 0;
+    for (frame, millis) in frames {
+      let end = start + millis;
+      svg.push_str(&format!(
+        r#"      <g visibility="hidden">
+        <animate attributeName="visibility" values="hidden;visible;hidden" keyTimes="0;{};{}" dur="{}ms" calcMode="discrete" repeatCount="indefinite" />
+"#,
+        start as f64 / duration as f64,
+        end as f64 / duration as f64,
+        duration));
+      for line in lines(frame) {
+        svg.push_str(&format!(
+          "        <text x=\"{}\" y=\"{}\" class=\"{}\">{}</text>\n",
+          line.x, line.y, class, escape(&line.text)));
+      }
+      svg.push_str("      </g>\n");
+      start = end;
+    }
+    svg.push_str("    </g>\n  </svg>\n");
+  }
+  svg.push_str("</svg>\n");
+
+  Ok(svg)
+}
+
+// The regular weight of each family, embedded so the SVG looks the same
+// wherever it's opened, without fetching anything.
+fn font_faces(config: &SiteConfig, families: &[&str]) -> Result<String, io::Error> {
+  let mut css = String::new();
+  for (i, family) in families.iter().enumerate() {
+    if families[..i].contains(family) {
+      continue;
+    }
+    let file = &config.fonts[*family].regular;
+    let path = Path::new(assets::FONTS).join(file);
+    let content_type = path
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .and_then(ContentType::from_extension)
+      .unwrap_or(ContentType::Binary);
+    writeln!(
+      css,
+      "    @font-face {{ font-family: \"{}\"; src: url(\"data:{};base64,{}\"); }}",
+      family, content_type, base64::encode(&fs::read(&path)?)).unwrap();
+  }
+
+  Ok(css)
+}
+
+// Picks one segment's lines out of a frame.
+type Lines = fn(&Frame) -> &Vec<Text>;
+
+fn hex(color: Rgb) -> String {
+  format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
@@ -0,0 +1,288 @@
+mod tokenize;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use code::SourceCodeDirectory;
+use writing::Sample;
+
+use self::tokenize::{Token, Tokenizer};
+
+
+const MAX_HIGHLIGHTED_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+  Writing,
+  Code,
+}
+
+struct Document {
+  kind: Kind,
+  title: String,
+  // The page the document is shown on.
+  url: String,
+  language: Option<String>,
+  lines: Vec<String>,
+}
+
+struct Occurrence {
+  document: usize,
+  line: usize,
+  start: usize,
+  end: usize,
+}
+
+pub struct Index {
+  tokenizer: Tokenizer,
+  documents: Vec<Document>,
+  postings: HashMap<String, Vec<Occurrence>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Fragment<'a> {
+  pub text: &'a str,
+  pub highlighted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineMatch<'a> {
+  // Counted from 1, as the pages number lines.
+  pub number: usize,
+  pub fragments: Vec<Fragment<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Hit<'a> {
+  pub kind: Kind,
+  pub title: &'a str,
+  pub url: &'a str,
+  pub language: Option<&'a str>,
+  pub score: f64,
+  pub lines: Vec<LineMatch<'a>>,
+}
+
+impl Index {
+  pub fn build(samples: &[Sample], source_dirs: &[SourceCodeDirectory]) -> Index {
+    let mut index = Index {
+      tokenizer: Tokenizer::new(),
+      documents: Vec::new(),
+      postings: HashMap::new(),
+    };
+
+    for sample in samples {
+      index.add(Document {
+        kind: Kind::Writing,
+        title: sample.file_name.clone(),
+        url: format!("/writing/{}", sample.slug),
+        language: None,
+        lines: sample.lines.iter().map(|line| line.text.clone()).collect(),
+      });
+    }
+    for dir in source_dirs {
+      for source in &dir.source_files {
+        index.add(Document {
+          kind: Kind::Code,
+          title: source.file_name.clone(),
+          url: format!("/code/{}/{}", dir.language_name, source.file_name),
+          language: Some(dir.language_name.clone()),
+          lines: source.lines_of_code.iter().map(|line| line.code.clone()).collect(),
+        });
+      }
+    }
+
+    index
+  }
+
+  pub fn search(&self, query: &str, limit: usize) -> Vec<Hit<'_>> {
+    let terms = self.tokenizer
+      .code(query)
+      .into_iter()
+      .map(|token| token.term)
+      .collect::<HashSet<String>>();
+
+    let mut scores = HashMap::new();
+    let mut spans = HashMap::new();
+    for term in &terms {
+      let occurrences = match self.postings.get(term) {
+        Some(occurrences) => occurrences,
+        None              => continue,
+      };
+
+      let mut frequencies = HashMap::new();
+      for occurrence in occurrences {
+        *frequencies.entry(occurrence.document).or_insert(0) += 1;
+        spans
+          .entry(occurrence.document)
+          .or_insert_with(BTreeMap::new)
+          .entry(occurrence.line)
+          .or_insert_with(Vec::new)
+          .push((occurrence.start, occurrence.end));
+      }
+
+      let idf = (1.0 + self.documents.len() as f64 / frequencies.len() as f64).ln();
+      for (document, frequency) in frequencies {
+        *scores.entry(document).or_insert(0.0) += (1.0 + (frequency as f64).ln()) * idf;
+      }
+    }
+
+    let mut ranked = scores.into_iter().collect::<Vec<(usize, f64)>>();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+
+    ranked
+      .into_iter()
+      .map(|(document, score)| {
+        let doc = &self.documents[document];
+        let lines = spans.remove(&document).unwrap_or_default();
+
+        Hit {
+          kind: doc.kind,
+          title: &doc.title,
+          url: &doc.url,
+          language: doc.language.as_deref(),
+          score,
+          lines: highlight(&doc.lines, lines),
+        }
+      })
+      .collect()
+  }
+
+  fn add(&mut self, document: Document) {
+    let id = self.documents.len();
+
+    for (line_number, line) in document.lines.iter().enumerate() {
+      let tokens = match document.kind {
+        Kind::Writing => self.tokenizer.prose(line),
+        Kind::Code    => self.tokenizer.code(line),
+      };
+
+      for Token { term, start, end } in tokens {
+        self.postings.entry(term).or_default().push(Occurrence {
+          document: id,
+          line: line_number,
+          start,
+          end,
+        });
+      }
+    }
+
+    self.documents.push(document);
+  }
+}
+
+// Pick the lines with the most matches and split each one into plain and
+// highlighted fragments.
+fn highlight(lines: &[String], spans: BTreeMap<usize, Vec<(usize, usize)>>) -> Vec<LineMatch<'_>> {
+  let mut best = spans.into_iter().collect::<Vec<(usize, Vec<(usize, usize)>)>>();
+  best.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+  best.truncate(MAX_HIGHLIGHTED_LINES);
+  best.sort_by_key(|&(line, _)| line);
+
+  best
+    .into_iter()
+    .map(|(line, line_spans)| LineMatch {
+      number: line + 1,
+      fragments: fragments(&lines[line], merge(line_spans)),
+    })
+    .collect()
+}
+
+// Identifiers are indexed alongside their parts, so spans can overlap.
+fn merge(mut spans: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+  spans.sort();
+
+  let mut merged: Vec<(usize, usize)> = Vec::new();
+  for (start, end) in spans {
+    match merged.last_mut() {
+      Some(last) if start <= last.1 => last.1 = last.1.max(end),
+      _                            => merged.push((start, end)),
+    }
+  }
+
+  merged
+}
+
+fn fragments(text: &str, spans: Vec<(usize, usize)>) -> Vec<Fragment<'_>> {
+  let mut fragments = Vec::new();
+  let mut position = 0;
+
+  for (start, end) in spans {
+    if start > position {
+      fragments.push(Fragment { text: &text[position..start], highlighted: false });
+    }
+    fragments.push(Fragment { text: &text[start..end], highlighted: true });
+    position = end;
+  }
+  if position < text.len() {
+    fragments.push(Fragment { text: &text[position..], highlighted: false });
+  }
+
+  fragments
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn index(documents: &[(Kind, &str, &[&str])]) -> Index {
+    let mut index = Index {
+      tokenizer: Tokenizer::new(),
+      documents: Vec::new(),
+      postings: HashMap::new(),
+    };
+    for (kind, title, lines) in documents {
+      index.add(Document {
+        kind: *kind,
+        title: title.to_string(),
+        url: format!("/writing/{}", title),
+        language: None,
+        lines: lines.iter().map(|line| line.to_string()).collect(),
+      });
+    }
+
+    index
+  }
+
+  #[test]
+  fn documents_matching_more_often_rank_higher() {
+    let index = index(&[
+      (Kind::Writing, "once.txt", &["Parsing is fun."]),
+      (Kind::Writing, "twice.txt", &["A parser parses.", "", "More parsing."]),
+      (Kind::Code, "main.rs", &["fn main() {}"]),
+    ]);
+    let hits = index.search("parsing", 10);
+
+    let titles = hits.iter().map(|hit| hit.title).collect::<Vec<&str>>();
+    assert_eq!(titles, vec!["twice.txt", "once.txt"]);
+    assert!(hits[0].score > hits[1].score);
+    assert_eq!(index.search("parsing", 1).len(), 1);
+    assert!(index.search("nowhere", 10).is_empty());
+  }
+
+  #[test]
+  fn matching_lines_are_numbered_from_one_and_highlighted() {
+    let index = index(&[(Kind::Writing, "twice.txt", &["A parser parses.", "", "More parsing."])]);
+    let hits = index.search("parse", 10);
+
+    let numbers = hits[0].lines.iter().map(|line| line.number).collect::<Vec<usize>>();
+    assert_eq!(numbers, vec![1, 3]);
+    let fragments = hits[0].lines[0].fragments
+      .iter()
+      .map(|fragment| (fragment.text, fragment.highlighted))
+      .collect::<Vec<(&str, bool)>>();
+    assert_eq!(fragments, vec![("A parser ", false), ("parses", true), (".", false)]);
+  }
+
+  #[test]
+  fn identifiers_are_found_by_their_parts() {
+    let index = index(&[(Kind::Code, "lib.rs", &["let ClinkResult = run();"])]);
+
+    for (query, highlighted) in &[("ClinkResult", "ClinkResult"), ("clink", "Clink"), ("results", "Result")] {
+      let hits = index.search(query, 10);
+      assert_eq!(hits.len(), 1, "{}", query);
+      assert_eq!(hits[0].lines[0].fragments[1].text, *highlighted, "{}", query);
+    }
+  }
+}
@@ -0,0 +1,174 @@
+use rust_stemmers::{Algorithm, Stemmer};
+
+use writing::is_stop_word;
+
+
+#[derive(Debug)]
+pub struct Token {
+  pub term: String,
+  pub start: usize,
+  pub end: usize,
+}
+
+pub struct Tokenizer {
+  stemmer: Stemmer,
+}
+
+impl Tokenizer {
+  pub fn new() -> Tokenizer {
+    Tokenizer {
+      stemmer: Stemmer::create(Algorithm::English),
+    }
+  }
+
+  // Split English text into stemmed words, skipping stop words.
+  pub fn prose(&self, text: &str) -> Vec<Token> {
+    runs(text, char::is_alphanumeric)
+      .into_iter()
+      .filter_map(|(start, end)| self.word(&text[start..end], start, end))
+      .collect()
+  }
+
+  // Split source code into identifiers, and identifiers into their parts, so
+  // that `ClinkResult` can be found by searching for "clink" or "results"
+  // as well as "ClinkResult".
+  pub fn code(&self, text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for (start, end) in runs(text, |c| c.is_alphanumeric() || c == '_') {
+      let identifier = &text[start..end];
+      let parts = identifier_parts(identifier);
+
+      if parts.len() > 1 {
+        tokens.push(Token {
+          term: identifier.to_lowercase(),
+          start,
+          end,
+        });
+      }
+      for (part_start, part_end) in parts {
+        let part = &identifier[part_start..part_end];
+        if let Some(token) = self.word(part, start + part_start, start + part_end) {
+          tokens.push(token);
+        }
+      }
+    }
+
+    tokens
+  }
+
+  fn word(&self, word: &str, start: usize, end: usize) -> Option<Token> {
+    let lowercase = word.to_lowercase();
+    if lowercase.chars().count() < 2 || is_stop_word(&lowercase) {
+      return None;
+    }
+
+    Some(Token {
+      term: self.stemmer.stem(&lowercase).into_owned(),
+      start,
+      end,
+    })
+  }
+}
+
+// Find the byte ranges of every maximal run of characters satisfying a predicate.
+fn runs<F: Fn(char) -> bool>(text: &str, predicate: F) -> Vec<(usize, usize)> {
+  let mut ranges = Vec::new();
+  let mut start = None;
+
+  for (offset, c) in text.char_indices() {
+    match (start, predicate(c)) {
+      (None, true)         => start = Some(offset),
+      (Some(s), false)     => {
+        ranges.push((s, offset));
+        start = None;
+      },
+      _                    => (),
+    }
+  }
+  if let Some(s) = start {
+    ranges.push((s, text.len()));
+  }
+
+  ranges
+}
+
+// Split an identifier on underscores, lower-to-upper case transitions, the end
+// of an acronym ("HTTPServer" -> "HTTP", "Server") and letter/digit boundaries.
+fn identifier_parts(identifier: &str) -> Vec<(usize, usize)> {
+  let chars = identifier.char_indices().collect::<Vec<(usize, char)>>();
+  let mut parts = Vec::new();
+  let mut start = None;
+
+  for (i, &(offset, c)) in chars.iter().enumerate() {
+    if c == '_' {
+      if let Some(s) = start.take() {
+        parts.push((s, offset));
+      }
+      continue;
+    }
+
+    match start {
+      None    => start = Some(offset),
+      Some(s) => {
+        let previous = chars[i - 1].1;
+        let next_is_lowercase = match chars.get(i + 1) {
+          Some(&(_, next)) => next.is_lowercase(),
+          None             => false,
+        };
+        let is_boundary = (previous.is_lowercase() && c.is_uppercase())
+          || (previous.is_uppercase() && c.is_uppercase() && next_is_lowercase)
+          || (previous.is_alphabetic() && c.is_numeric())
+          || (previous.is_numeric() && c.is_alphabetic());
+
+        if is_boundary {
+          parts.push((s, offset));
+          start = Some(offset);
+        }
+      },
+    }
+  }
+  if let Some(s) = start {
+    parts.push((s, identifier.len()));
+  }
+
+  parts
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn terms(tokens: Vec<Token>) -> Vec<String> {
+    tokens.into_iter().map(|token| token.term).collect()
+  }
+
+  fn parts(identifier: &str) -> Vec<&str> {
+    identifier_parts(identifier)
+      .into_iter()
+      .map(|(start, end)| &identifier[start..end])
+      .collect()
+  }
+
+  #[test]
+  fn prose_is_stemmed_without_stop_words() {
+    let tokenizer = Tokenizer::new();
+
+    assert_eq!(terms(tokenizer.prose("The runners were running quickly")), vec!["runner", "run", "quick"]);
+    assert_eq!(terms(tokenizer.prose("parses, parsing and parsed")), vec!["pars", "pars", "pars"]);
+  }
+
+  #[test]
+  fn identifiers_are_split_into_their_parts() {
+    assert_eq!(parts("ClinkResult"), vec!["Clink", "Result"]);
+    assert_eq!(parts("HTTPServer"), vec!["HTTP", "Server"]);
+    assert_eq!(parts("snake_case_name"), vec!["snake", "case", "name"]);
+    assert_eq!(parts("utf8Decode"), vec!["utf", "8", "Decode"]);
+    assert_eq!(parts("_private"), vec!["private"]);
+
+    // Code is indexed by whole identifiers as well as their stemmed parts.
+    let tokens = Tokenizer::new().code("let ClinkResults = x;");
+    assert_eq!((tokens[0].start, tokens[0].end), (4, 16));
+    assert_eq!(terms(tokens), vec!["clinkresults", "clink", "result"]);
+  }
+}
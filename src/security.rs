@@ -0,0 +1,262 @@
+use std::io::Cursor;
+
+use base64;
+use rocket::{Request, Response, Rocket, State};
+use rocket::config::{Config, Table, Value};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use sha2::{Digest, Sha256};
+
+use caching::longest_prefix;
+
+
+const CONTENT_SECURITY_POLICY: &str = "default-src 'self'";
+const FRAME_ANCESTORS: &str = "'none'";
+const STRICT_TRANSPORT_SECURITY: &str = "max-age=63072000; includeSubDomains";
+const CONTENT_TYPE_OPTIONS: &str = "nosniff";
+const REFERRER_POLICY: &str = "strict-origin-when-cross-origin";
+const PERMISSIONS_POLICY: &str = "camera=(), microphone=(), geolocation=(), interest-cohort=()";
+
+// The headers sent with a response. An empty value leaves a header out.
+#[derive(Clone, Debug)]
+pub struct Policy {
+  content_security_policy: String,
+  frame_ancestors: String,
+  strict_transport_security: String,
+  content_type_options: String,
+  referrer_policy: String,
+  permissions_policy: String,
+}
+
+impl Default for Policy {
+  fn default() -> Policy {
+    Policy {
+      content_security_policy: CONTENT_SECURITY_POLICY.to_string(),
+      frame_ancestors: FRAME_ANCESTORS.to_string(),
+      strict_transport_security: STRICT_TRANSPORT_SECURITY.to_string(),
+      content_type_options: CONTENT_TYPE_OPTIONS.to_string(),
+      referrer_policy: REFERRER_POLICY.to_string(),
+      permissions_policy: PERMISSIONS_POLICY.to_string(),
+    }
+  }
+}
+
+impl Policy {
+  fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+    let value = value
+      .as_str()
+      .ok_or_else(|| format!("security.{} must be a string", key))?
+      .to_string();
+
+    match key {
+      "content_security_policy"   => self.content_security_policy = value,
+      "frame_ancestors"           => self.frame_ancestors = value,
+      "strict_transport_security" => self.strict_transport_security = value,
+      "content_type_options"      => self.content_type_options = value,
+      "referrer_policy"           => self.referrer_policy = value,
+      "permissions_policy"        => self.permissions_policy = value,
+      _                           => return Err(format!("Unknown security header setting `{}`", key)),
+    }
+
+    Ok(())
+  }
+
+  // Header values for a response, allowing the given inline `<style>` and
+  // `<script>` hashes.
+  fn headers(&self, style_hashes: &[String], script_hashes: &[String]) -> Vec<(&'static str, String)> {
+    let mut csp = self.content_security_policy.clone();
+    if !csp.is_empty() {
+      if !self.frame_ancestors.is_empty() && directive(&csp, "frame-ancestors").is_none() {
+        csp = format!("{}; frame-ancestors {}", csp, self.frame_ancestors);
+      }
+      csp = with_sources(&csp, "style-src", style_hashes);
+      csp = with_sources(&csp, "script-src", script_hashes);
+    }
+
+    vec![
+      ("Content-Security-Policy", csp),
+      ("Strict-Transport-Security", self.strict_transport_security.clone()),
+      ("X-Content-Type-Options", self.content_type_options.clone()),
+      ("Referrer-Policy", self.referrer_policy.clone()),
+      ("Permissions-Policy", self.permissions_policy.clone()),
+    ]
+  }
+}
+
+// Security headers configured in Rocket.toml, with overrides for any path
+// prefix under `routes`:
+//
+//   [global.security]
+//   content_security_policy = "default-src 'self'"
+//   frame_ancestors = "'none'"
+//
+//   [global.security.routes."/api/"]
+//   content_security_policy = "default-src 'none'"
+pub struct SecurityPolicy {
+  default: Policy,
+  routes: Vec<(String, Policy)>,
+}
+
+impl SecurityPolicy {
+  pub fn from_config(config: &Config) -> Result<SecurityPolicy, String> {
+    let mut default = Policy::default();
+    let mut overrides = Vec::new();
+
+    if let Ok(table) = config.get_table("security") {
+      for (key, value) in table {
+        if key == "routes" {
+          let routes = value
+            .as_table()
+            .ok_or_else(|| "security.routes must be a table".to_string())?;
+          for (prefix, settings) in routes {
+            overrides.push((prefix, route_table(prefix, settings)?));
+          }
+        } else {
+          default.set(key, value)?;
+        }
+      }
+    }
+
+    // Route overrides start from the defaults, wherever they appear.
+    let mut routes = Vec::new();
+    for (prefix, settings) in overrides {
+      let mut policy = default.clone();
+      for (key, value) in settings {
+        policy.set(key, value)?;
+      }
+      routes.push((prefix.to_string(), policy));
+    }
+
+    Ok(SecurityPolicy { default, routes })
+  }
+
+  pub fn for_path(&self, path: &str) -> &Policy {
+    longest_prefix(&self.routes, path).unwrap_or(&self.default)
+  }
+}
+
+// Adds the configured security headers to every response, including errors.
+pub struct SecurityHeaders;
+
+impl Fairing for SecurityHeaders {
+  fn info(&self) -> Info {
+    Info {
+      name: "Security headers",
+      kind: Kind::Attach | Kind::Response,
+    }
+  }
+
+  fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+    match SecurityPolicy::from_config(rocket.config()) {
+      Ok(policy) => Ok(rocket.manage(policy)),
+      Err(err)   => {
+        eprintln!("Invalid security configuration: {}", err);
+        Err(rocket)
+      },
+    }
+  }
+
+  fn on_response(&self, request: &Request, response: &mut Response) {
+    let policy = match request.guard::<State<SecurityPolicy>>().succeeded() {
+      Some(policy) => policy,
+      None         => return,
+    };
+
+    let (mut style_hashes, mut script_hashes) = (Vec::new(), Vec::new());
+    if response.content_type() == Some(ContentType::HTML) {
+      if let Some(body) = response.body_string() {
+        style_hashes = inline_hashes(&body, "style");
+        script_hashes = inline_hashes(&body, "script");
+        response.set_sized_body(Cursor::new(body));
+      }
+    }
+
+    for (name, value) in policy.for_path(request.uri().path()).headers(&style_hashes, &script_hashes) {
+      if value.is_empty() {
+        response.remove_header(name);
+      } else {
+        response.set_raw_header(name, value);
+      }
+    }
+  }
+}
+
+fn route_table<'a>(prefix: &str, settings: &'a Value) -> Result<&'a Table, String> {
+  if !prefix.starts_with('/') {
+    return Err(format!("security.routes.{} must be a path", prefix));
+  }
+
+  settings
+    .as_table()
+    .ok_or_else(|| format!("security.routes.{} must be a table", prefix))
+}
+
+// CSP source expressions for the contents of each inline element, such as
+// `'sha256-...'` for every `<style>` block. Scripts loaded by `src` are
+// covered by `'self'` instead.
+fn inline_hashes(html: &str, element: &str) -> Vec<String> {
+  let open = format!("<{}", element);
+  let close = format!("</{}>", element);
+  let mut hashes = Vec::new();
+  let mut rest = html;
+
+  while let Some(start) = rest.find(&open) {
+    let tag_end = match rest[start..].find('>') {
+      Some(end) => start + end + 1,
+      None      => break,
+    };
+    let content_end = match rest[tag_end..].find(&close) {
+      Some(end) => tag_end + end,
+      None      => break,
+    };
+
+    let tag = &rest[start..tag_end];
+    let content = &rest[tag_end..content_end];
+    if !tag.contains(" src=") && !content.trim().is_empty() {
+      let hash = format!("'sha256-{}'", base64::encode(&Sha256::digest(content.as_bytes())));
+      if !hashes.contains(&hash) {
+        hashes.push(hash);
+      }
+    }
+    rest = &rest[content_end + close.len()..];
+  }
+
+  hashes
+}
+
+fn directive<'a>(csp: &'a str, name: &str) -> Option<&'a str> {
+  csp
+    .split(';')
+    .map(str::trim)
+    .find(|directive| directive.split_whitespace().next() == Some(name))
+}
+
+// Add sources to a directive, starting it from `default-src` when the policy
+// doesn't set it, as browsers would.
+fn with_sources(csp: &str, name: &str, sources: &[String]) -> String {
+  if sources.is_empty() {
+    return csp.to_string();
+  }
+  let extra = sources.join(" ");
+
+  if directive(csp, name).is_some() {
+    return csp
+      .split(';')
+      .map(str::trim)
+      .filter(|directive| !directive.is_empty())
+      .map(|directive| if directive.split_whitespace().next() == Some(name) {
+        format!("{} {}", directive, extra)
+      } else {
+        directive.to_string()
+      })
+      .collect::<Vec<String>>()
+      .join("; ");
+  }
+
+  let fallback = directive(csp, "default-src")
+    .map(|default| default["default-src".len()..].trim())
+    .filter(|sources| !sources.is_empty() && *sources != "'none'")
+    .unwrap_or("");
+  let sources = format!("{} {}", fallback, extra);
+  format!("{}; {} {}", csp, name, sources.trim())
+}
@@ -0,0 +1,133 @@
+use rocket::{Outcome, Request, State};
+use rocket::request::{self, FromRequest};
+
+use code::{self, Source, SourceCodeDirectory};
+use config::{Animation, SiteConfig};
+use i18n::Locale;
+use pages;
+use writing::{self, Sample};
+
+
+// A line of text in one of the index page's diagonal segments, positioned in
+// the units of the segment's 100 by 100 viewBox.
+#[derive(Debug, Serialize)]
+pub struct Text {
+  pub x: f64,
+  pub y: f64,
+  pub text: String,
+}
+
+// The first frame of the segments' animation, so the page has something in
+// them before `js/main.js` runs, or if it never does.
+#[derive(Debug, Serialize)]
+pub struct Frame {
+  pub language: String,
+  pub file_name: String,
+  pub slug: String,
+  // What each segment starts on, for screen readers.
+  pub code_description: String,
+  pub writing_description: String,
+  pub code: Vec<Text>,
+  pub writing: Vec<Text>,
+}
+
+impl Frame {
+  // The script starts from whatever the server drew, so the first source and
+  // sample are drawn here, and it picks others at random once they're done.
+  // The sample is in `locale` if it's been translated, as the script's are.
+  pub fn first(animation: &Animation, samples: &[Sample], source_dirs: &[SourceCodeDirectory], locale: &str) -> Frame {
+    let mut frame = Frame {
+      language: String::new(),
+      file_name: String::new(),
+      slug: String::new(),
+      code_description: String::new(),
+      writing_description: String::new(),
+      code: Vec::new(),
+      writing: Vec::new(),
+    };
+
+    let first_source = source_dirs
+      .iter()
+      .flat_map(|dir| dir.source_files.first().map(|source| (dir, source)))
+      .next();
+    if let Some((dir, source)) = first_source {
+      frame.language = dir.language_name.clone();
+      frame.file_name = source.file_name.clone();
+      frame.code_description = describe_source(source);
+      frame.code = code_lines(animation, &source.lines_of_code);
+    }
+    if let Some(sample) = samples.first().map(|sample| sample.in_language(locale)) {
+      frame.slug = sample.slug.clone();
+      frame.writing_description = format!("“{}”", sample.title);
+      frame.writing = writing_lines(animation, &sample.lines);
+    }
+
+    frame
+  }
+}
+
+// The first frame in the language a page is served in.
+impl<'a, 'r> FromRequest<'a, 'r> for Frame {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Frame, ()> {
+    let locale = request.guard::<Locale>()?;
+    let config = request.guard::<State<SiteConfig>>()?;
+    let samples = request.guard::<State<Vec<Sample>>>()?;
+    let source_dirs = request.guard::<State<Vec<SourceCodeDirectory>>>()?;
+
+    Outcome::Success(Frame::first(&config.animation, &samples, &source_dirs, &locale))
+  }
+}
+
+// A source file by name, and the repository it came from if it's known.
+pub fn describe_source(source: &Source) -> String {
+  match source.provenance() {
+    Some(provenance) => format!("{} from {}/{}", pages::code::display_name(source), provenance.owner, provenance.repository),
+    None             => pages::code::display_name(source),
+  }
+}
+
+// Code lines run down the segment, each indented by `line_delta` per level,
+// as `code()` in `js/main.js` places them.
+pub fn code_lines(animation: &Animation, lines: &[code::Line]) -> Vec<Text> {
+  lines
+    .iter()
+    .take(animation.window_lines as usize)
+    .enumerate()
+    .map(|(number, line)| code_text(animation, number, line))
+    .collect()
+}
+
+pub fn code_text(animation: &Animation, number: usize, line: &code::Line) -> Text {
+  let [origin_x, origin_y] = animation.code_origin;
+  let [delta_x, delta_y] = animation.line_delta;
+
+  Text {
+    x: line.indent as f64 * delta_x + origin_x,
+    y: number as f64 * delta_y + origin_y,
+    text: line.code.clone(),
+  }
+}
+
+// Writing climbs the segment from its bottom corner, as `writing()` in
+// `js/main.js` places it.
+pub fn writing_lines(animation: &Animation, lines: &[writing::Line]) -> Vec<Text> {
+  lines
+    .iter()
+    .take(animation.window_lines as usize)
+    .enumerate()
+    .map(|(number, line)| writing_text(animation, number, line))
+    .collect()
+}
+
+pub fn writing_text(animation: &Animation, number: usize, line: &writing::Line) -> Text {
+  let [origin_x, origin_y] = animation.writing_origin;
+  let [delta_x, delta_y] = animation.line_delta;
+
+  Text {
+    x: origin_x + 0.5 * number as f64 * delta_x,
+    y: origin_y - number as f64 * delta_y,
+    text: line.text.clone(),
+  }
+}
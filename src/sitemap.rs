@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rocket::{Rocket, Route, State};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Method};
+use rocket::response::content::{Content, Plain};
+
+use code::SourceCodeDirectory;
+use config::SiteConfig;
+use pages::escape;
+use resume::Resume;
+use writing::Sample;
+
+
+#[get("/sitemap.xml")]
+pub fn sitemap_xml(sitemap: State<Sitemap>) -> Content<String> {
+  Content(ContentType::XML, sitemap.to_xml())
+}
+
+#[get("/robots.txt")]
+pub fn robots_txt(sitemap: State<Sitemap>) -> Plain<String> {
+  Plain(sitemap.robots_txt())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+  pub path: String,
+  pub updated: Option<DateTime<Utc>>,
+}
+
+// Every page a crawler should find, and the paths it should stay out of.
+#[derive(Clone, Debug)]
+pub struct Sitemap {
+  url: String,
+  pages: Vec<Page>,
+  disallow: Vec<String>,
+}
+
+impl Sitemap {
+  // Pages are the routes with a fixed path that don't look like files, plus
+  // every article and source file. A route's lastmod is that of the newest
+  // page under it.
+  pub fn build<'a, I>(
+    config: &SiteConfig,
+    routes: I,
+    resume: &Resume,
+    samples: &[Sample],
+    source_dirs: &[SourceCodeDirectory],
+  ) -> Sitemap
+    where I: IntoIterator<Item = &'a Route>
+  {
+    let disallow = config.robots.disallow.clone();
+    let allowed = |path: &str| !disallow.iter().any(|prefix| path.starts_with(prefix.as_str()));
+
+    let mut content = vec![Page { path: "/resume".to_string(), updated: resume.updated }];
+    for sample in samples {
+      content.push(Page { path: format!("/writing/{}", sample.slug), updated: Some(sample.updated) });
+    }
+    for dir in source_dirs {
+      for source in &dir.source_files {
+        content.push(Page {
+          path: format!("/code/{}/{}", dir.language_name, source.file_name),
+          updated: Some(source.updated),
+        });
+      }
+    }
+
+    let mut pages = BTreeMap::new();
+    for route in routes {
+      let path = route.uri.path();
+      let file_like = path.rsplit('/').next().map(|name| name.contains('.')).unwrap_or(false);
+      if route.method != Method::Get || path.contains('<') || file_like || !allowed(path) {
+        continue;
+      }
+
+      let updated = content
+        .iter()
+        .filter(|page| path == "/" || page.path == path || page.path.starts_with(&format!("{}/", path)))
+        .filter_map(|page| page.updated)
+        .max();
+      pages.insert(path.to_string(), updated);
+    }
+    for page in content {
+      if allowed(&page.path) {
+        pages.entry(page.path).or_insert(page.updated);
+      }
+    }
+
+    Sitemap {
+      url: config.site.url.clone(),
+      pages: pages
+        .into_iter()
+        .map(|(path, updated)| Page { path, updated })
+        .collect(),
+      disallow,
+    }
+  }
+
+  pub fn pages(&self) -> &[Page] {
+    &self.pages
+  }
+
+  pub fn to_xml(&self) -> String {
+    let mut xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#.to_string();
+
+    for page in &self.pages {
+      xml.push_str(&format!("  <url>\n    <loc>{}{}</loc>\n", escape(&self.url), escape(&page.path)));
+      if let Some(updated) = page.updated {
+        xml.push_str(&format!("    <lastmod>{}</lastmod>\n", updated.to_rfc3339_opts(SecondsFormat::Secs, true)));
+      }
+      xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    xml
+  }
+
+  pub fn robots_txt(&self) -> String {
+    let mut robots = "User-agent: *\n".to_string();
+    if self.disallow.is_empty() {
+      robots.push_str("Disallow:\n");
+    }
+    for path in &self.disallow {
+      robots.push_str(&format!("Disallow: {}\n", path));
+    }
+    robots.push_str(&format!("\nSitemap: {}/sitemap.xml\n", self.url));
+
+    robots
+  }
+}
+
+// Builds the sitemap from every route mounted before it's attached, along
+// with the site's content, and serves it with robots.txt.
+pub struct Crawling;
+
+impl Fairing for Crawling {
+  fn info(&self) -> Info {
+    Info {
+      name: "Sitemap and robots.txt",
+      kind: Kind::Attach,
+    }
+  }
+
+  fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+    let sitemap = match (
+      rocket.state::<SiteConfig>(),
+      rocket.state::<Resume>(),
+      rocket.state::<Vec<Sample>>(),
+      rocket.state::<Vec<SourceCodeDirectory>>(),
+    ) {
+      (Some(config), Some(resume), Some(samples), Some(source_dirs)) =>
+        Sitemap::build(config, rocket.routes(), resume, samples, source_dirs),
+      _ => {
+        eprintln!("The sitemap needs the site configuration and content to be managed first");
+        return Err(rocket);
+      },
+    };
+
+    Ok(rocket.manage(sitemap).mount("/", routes![sitemap_xml, robots_txt]))
+  }
+}
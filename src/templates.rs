@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+use tera::{Context, Tera};
+
+use config::{Site, SiteConfig};
+use i18n::Catalogs;
+use pages::escape;
+
+
+// Every template a route renders, so a missing one is caught at startup
+// rather than on the first request for it.
+const PAGES: &[&str] = &[
+  "index.html",
+  "resume.html",
+  "writing/index.html",
+  "writing/article.html",
+  "code/index.html",
+  "code/source.html",
+  "transcript.html",
+  "errors/404.html",
+  "errors/500.html",
+];
+
+pub struct Templates {
+  tera: Tera,
+  site: Site,
+  themes: Vec<String>,
+  catalogs: Catalogs,
+}
+
+impl Templates {
+  // Parse every template under `dir`, failing on syntax errors, unknown
+  // parents and missing pages. Every page is rendered with `site`, the
+  // themes a visitor can choose from, and the messages from `catalogs` in the
+  // language it's rendered in as `t`.
+  pub fn load<P: AsRef<Path>>(dir: P, config: &SiteConfig, catalogs: Catalogs) -> Result<Templates, io::Error> {
+    let glob = dir.as_ref().join("**").join("*");
+    let mut tera = Tera::new(&glob.to_string_lossy()).map_err(to_io_error)?;
+    tera.set_escape_fn(escape);
+
+    let loaded = tera.get_template_names().collect::<Vec<&str>>();
+    for page in PAGES {
+      if !loaded.contains(page) {
+        return Err(io::Error::new(
+          io::ErrorKind::NotFound,
+          format!("Template {} is missing from {}", page, dir.as_ref().display())));
+      }
+    }
+
+    Ok(Templates {
+      tera,
+      site: config.site.clone(),
+      themes: config.themes.keys().cloned().collect(),
+      catalogs,
+    })
+  }
+
+  pub fn catalogs(&self) -> &Catalogs {
+    &self.catalogs
+  }
+
+  pub fn message(&self, language: &str, key: &str) -> String {
+    self.catalogs.message(language, key)
+  }
+
+  pub fn render(&self, name: &str, mut context: Context, language: &str) -> Result<String, io::Error> {
+    let languages = self.catalogs
+      .languages()
+      .into_iter()
+      .map(|code| json!({
+        "code": code,
+        "name": self.message(code, "language.name"),
+        "query": self.catalogs.query(code),
+      }))
+      .collect::<Vec<Value>>();
+
+    // A catalog can describe the site in its own language.
+    let messages = self.catalogs.messages(language);
+    let mut site = self.site.clone();
+    if let Some(description) = messages.pointer("/site/description").and_then(Value::as_str) {
+      site.description = description.to_string();
+    }
+
+    // Themes are named in the settings, and have a name in the catalog only
+    // if someone's given them one.
+    let themes = self.themes
+      .iter()
+      .map(|name| json!({
+        "name": name,
+        "label": messages.pointer(&format!("/themes/{}", name)).and_then(Value::as_str).unwrap_or(name),
+      }))
+      .collect::<Vec<Value>>();
+
+    context.insert("site", &site);
+    context.insert("lang", language);
+    context.insert("lang_query", &self.catalogs.query(language));
+    context.insert("languages", &languages);
+    context.insert("themes", &themes);
+    context.insert("t", messages);
+
+    self.tera.render(name, &context).map_err(to_io_error)
+  }
+}
+
+// Tera reports the useful part of an error, like the line of a syntax error,
+// in its sources.
+fn to_io_error(err: tera::Error) -> io::Error {
+  let mut message = err.to_string();
+  let mut source = err.source();
+  while let Some(cause) = source {
+    message.push_str(": ");
+    message.push_str(&cause.to_string());
+    source = cause.source();
+  }
+
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,1221 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rocket;
+use rocket::Response;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::{Client, LocalResponse};
+use rocket::response::Responder;
+use serde_json;
+
+use assets;
+use code;
+use compression::{self, Encoding};
+use config::{self, SiteConfig};
+use contact::Contact;
+use export;
+use feed;
+use fingerprint::Manifest;
+use highlight;
+use i18n::Catalogs;
+use motion::ByMotion;
+use raster;
+use redirects::Rules;
+use resume::{self, Negotiated, Resume};
+use scroll;
+use templates::Templates;
+use themes;
+use writing;
+
+
+fn client() -> Client {
+  let config = SiteConfig::load().unwrap();
+  let templates = Templates::load(&config.paths.templates, &config, catalogs(&config)).unwrap();
+  let resume = Resume::load(&config.paths.resume).unwrap();
+  let source_dirs = code::analyze(&config.paths.snippets).unwrap();
+  let writing_samples = writing::collect(&config.paths.writing, &catalogs(&config)).unwrap();
+  let manifest = Manifest::build(assets::ROOTS).unwrap();
+
+  Client::new(super::rocket(config, templates, resume, writing_samples, source_dirs, manifest)).unwrap()
+}
+
+fn catalogs(config: &SiteConfig) -> Catalogs {
+  Catalogs::load(&config.paths.locales, &config.site.language).unwrap()
+}
+
+// A client serving nothing but `dir`, as an asset root at `/assets`.
+fn asset_client(dir: &Path) -> Client {
+  let root = assets::AssetRoot::new(dir.to_str().unwrap()).unwrap();
+  Client::new(rocket::ignite().mount("/assets", root)).unwrap()
+}
+
+// One URL for every route mounted in `rocket()`, plus a miss for the catcher.
+fn urls(client: &Client) -> Vec<String> {
+  let manifest = client.rocket().state::<Manifest>().unwrap();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let source_dir = &client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap()[0];
+
+  vec![
+    "/".to_string(),
+    "/resume".to_string(),
+    "/resume.txt".to_string(),
+    "/resume.md".to_string(),
+    "/resume.json".to_string(),
+    "/resume.pdf".to_string(),
+    "/api/writing".to_string(),
+    "/search?q=rust".to_string(),
+    "/feed.atom".to_string(),
+    "/feed.rss".to_string(),
+    "/sitemap.xml".to_string(),
+    "/preview/index.png".to_string(),
+    "/robots.txt".to_string(),
+    "/writing".to_string(),
+    format!("/writing/{}", sample.slug),
+    "/code".to_string(),
+    "/transcript".to_string(),
+    format!("/code/{}/{}", source_dir.language_name, source_dir.source_files[0].file_name),
+    "/css/main.css".to_string(),
+    "/fonts/DejaVuSans.ttf".to_string(),
+    format!("/{}", manifest.fingerprinted("js/main.js").unwrap()),
+    "/no/such/page".to_string(),
+  ]
+}
+
+fn header(response: &LocalResponse, name: &str) -> String {
+  response
+    .headers()
+    .get_one(name)
+    .unwrap_or_else(|| panic!("{} is missing", name))
+    .to_string()
+}
+
+#[test]
+fn every_route_sends_security_headers() {
+  let client = client();
+
+  for url in urls(&client) {
+    let response = client.get(url.clone()).dispatch();
+    assert!(response.status() == Status::Ok || url == "/no/such/page", "{} failed", url);
+
+    let csp = header(&response, "Content-Security-Policy");
+    assert!(csp.contains("frame-ancestors 'none'"), "{} has CSP {}", url, csp);
+    assert!(header(&response, "Strict-Transport-Security").starts_with("max-age="));
+    assert_eq!(header(&response, "X-Content-Type-Options"), "nosniff");
+    assert_eq!(header(&response, "Referrer-Policy"), "strict-origin-when-cross-origin");
+    assert!(header(&response, "Permissions-Policy").contains("camera=()"));
+  }
+}
+
+#[test]
+fn resume_inline_style_is_allowed_by_hash() {
+  let client = client();
+  let response = client.get("/resume").dispatch();
+  let csp = header(&response, "Content-Security-Policy");
+
+  let style_src = csp
+    .split(';')
+    .map(str::trim)
+    .find(|directive| directive.starts_with("style-src "))
+    .unwrap();
+  assert!(style_src.contains("'self'"));
+  assert!(style_src.contains("'sha256-"));
+  assert!(!style_src.contains("'unsafe-inline'"));
+}
+
+#[test]
+fn search_hits_link_to_their_pages() {
+  let client = client();
+  let body = client.get("/search?q=result&limit=100").dispatch().body_string().unwrap();
+  let hits = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+  let hits = hits.as_array().unwrap();
+
+  for kind in &["writing", "code"] {
+    assert!(hits.iter().any(|hit| hit["kind"] == *kind), "no {} hits", kind);
+  }
+  for hit in hits {
+    let url = hit["url"].as_str().unwrap();
+    if hit["kind"] == "code" {
+      assert_eq!(url, format!("/code/{}/{}", hit["language"].as_str().unwrap(), hit["title"].as_str().unwrap()));
+    } else {
+      assert!(url.starts_with("/writing/"), "{}", url);
+    }
+    assert_eq!(client.get(url).dispatch().status(), Status::Ok, "{}", url);
+  }
+}
+
+#[test]
+fn routes_can_override_the_policy() {
+  let client = client();
+
+  for url in &["/api/writing", "/search?q=rust"] {
+    let response = client.get(*url).dispatch();
+    let csp = header(&response, "Content-Security-Policy");
+    assert!(csp.starts_with("default-src 'none'"), "{} has CSP {}", url, csp);
+    assert_eq!(header(&response, "X-Content-Type-Options"), "nosniff");
+  }
+
+  let response = client.get("/").dispatch();
+  assert!(header(&response, "Content-Security-Policy").starts_with("default-src 'self'"));
+}
+
+#[test]
+fn not_modified_responses_keep_security_headers() {
+  let client = client();
+  let etag = header(&client.get("/writing").dispatch(), "ETag");
+
+  let response = client
+    .get("/writing")
+    .header(Header::new("If-None-Match", etag))
+    .dispatch();
+  assert_eq!(response.status(), Status::NotModified);
+  assert_eq!(header(&response, "X-Content-Type-Options"), "nosniff");
+}
+
+#[test]
+fn each_encoding_has_its_own_etag() {
+  let client = client();
+  let encoded = |encoding: &str| client.get("/writing").header(Header::new("Accept-Encoding", encoding.to_string())).dispatch();
+
+  let brotli = encoded("br");
+  assert_eq!(header(&brotli, "Content-Encoding"), "br");
+  let etag = header(&brotli, "ETag");
+  assert_ne!(header(&encoded("gzip"), "ETag"), etag);
+  assert_ne!(header(&encoded("identity"), "ETag"), etag);
+
+  let response = client
+    .get("/writing")
+    .header(Header::new("Accept-Encoding", "br"))
+    .header(Header::new("If-None-Match", etag))
+    .dispatch();
+  assert_eq!(response.status(), Status::NotModified);
+}
+
+#[test]
+fn assets_are_revalidated_and_cached_by_their_names() {
+  let client = client();
+  let response = client.get("/css/main.css").dispatch();
+  let (etag, modified) = (header(&response, "ETag"), header(&response, "Last-Modified"));
+  assert_eq!(header(&response, "Cache-Control"), "public, max-age=3600");
+
+  let revalidations = &[
+    ("If-None-Match", etag.as_str(), Status::NotModified),
+    ("If-None-Match", "\"stale\"", Status::Ok),
+    ("If-Modified-Since", modified.as_str(), Status::NotModified),
+    ("If-Modified-Since", "Thu, 01 Jan 1970 00:00:00 GMT", Status::Ok),
+  ];
+  for (name, value, status) in revalidations {
+    let response = client.get("/css/main.css").header(Header::new(*name, value.to_string())).dispatch();
+    assert_eq!(response.status(), *status, "{}: {}", name, value);
+  }
+
+  // Fingerprinted names are cached forever, and everything else revalidated.
+  let manifest = client.rocket().state::<Manifest>().unwrap();
+  let fingerprinted = client.get(format!("/{}", manifest.fingerprinted("css/main.css").unwrap())).dispatch();
+  assert_eq!(header(&fingerprinted, "Cache-Control"), "public, max-age=31536000, immutable");
+  assert_eq!(header(&client.get("/writing").dispatch(), "Cache-Control"), "no-cache");
+}
+
+#[test]
+fn precompressed_assets_are_negotiated() {
+  let dir = env::temp_dir().join("homupage-encodings");
+  let css = dir.join("site.css");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(&css, "body {}").unwrap();
+  fs::write(compression::sibling_path(&css, "br"), "brotli").unwrap();
+  fs::write(compression::sibling_path(&css, "gz"), "gzip").unwrap();
+  let client = asset_client(&dir);
+
+  let negotiated = &[
+    ("gzip, br", "brotli"),
+    ("br;q=0.5, gzip", "gzip"),
+    ("br;q=0, gzip", "gzip"),
+    ("br;q=0, gzip;q=0", "body {}"),
+    ("*", "brotli"),
+    ("identity", "body {}"),
+  ];
+  for (accept, body) in negotiated {
+    let mut response = client.get("/assets/site.css").header(Header::new("Accept-Encoding", *accept)).dispatch();
+    assert_eq!(response.body_string().unwrap(), *body, "{}", accept);
+  }
+
+  // A sibling older than its original is stale, and left alone.
+  let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+  while modified(&css) <= modified(&compression::sibling_path(&css, "br")) {
+    thread::sleep(Duration::from_millis(10));
+    fs::write(&css, "body { margin: 0 }").unwrap();
+  }
+  assert_eq!(compression::sibling(&css, Encoding::Brotli), None);
+  let mut response = client.get("/assets/site.css").header(Header::new("Accept-Encoding", "br")).dispatch();
+  assert!(response.headers().get_one("Content-Encoding").is_none());
+  assert_eq!(response.body_string().unwrap(), "body { margin: 0 }");
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unknown_urls_get_a_not_found_page() {
+  let client = client();
+  let mut response = client.get("/no/such/page").dispatch();
+
+  assert_eq!(response.status(), Status::NotFound);
+  assert!(response.body_string().unwrap().contains("<code>/no/such/page</code>"));
+}
+
+#[test]
+fn assets_outside_their_root_are_not_found() {
+  let client = client();
+  for url in &["/css/../Cargo.toml", "/css/%2e%2e/Cargo.toml", "/css/%2E%2E%2FCargo.toml"] {
+    assert_eq!(client.get(*url).dispatch().status(), Status::NotFound, "{}", url);
+  }
+
+  // Nor can a symlink lead out of one.
+  let dir = env::temp_dir().join("homupage-asset-root");
+  let outside = env::temp_dir().join("homupage-outside-the-root.css");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("inside.css"), "body {}").unwrap();
+  fs::write(&outside, "body {}").unwrap();
+  let link = dir.join("outside.css");
+  let _ = fs::remove_file(&link);
+  unix::fs::symlink(&outside, &link).unwrap();
+
+  let client = asset_client(&dir);
+  assert_eq!(client.get("/assets/inside.css").dispatch().status(), Status::Ok);
+  assert_eq!(client.get("/assets/outside.css").dispatch().status(), Status::NotFound);
+  fs::remove_dir_all(&dir).unwrap();
+  fs::remove_file(&outside).unwrap();
+}
+
+#[test]
+fn legacy_urls_redirect() {
+  let client = client();
+  let redirects = &[
+    ("/resume.html", Status::MovedPermanently, "/resume"),
+    ("/cv?lang=en", Status::MovedPermanently, "/resume?lang=en"),
+    ("/blog/on-rust", Status::MovedPermanently, "/writing/on-rust"),
+    ("/posts/2016/09/on-rust", Status::MovedPermanently, "/writing/on-rust"),
+    ("/snippets/go/main.go", Status::Found, "/code/go/main.go"),
+  ];
+
+  for (from, status, to) in redirects {
+    let response = client.get(*from).dispatch();
+    assert_eq!(response.status(), *status, "{}", from);
+    assert_eq!(header(&response, "Location"), *to);
+  }
+}
+
+#[test]
+fn aliases_serve_their_target() {
+  let client = client();
+  let mut response = client.get("/atom.xml").dispatch();
+
+  assert_eq!(response.status(), Status::Ok);
+  assert!(response.body_string().unwrap().contains("<feed"));
+}
+
+#[test]
+fn redirect_rules_match_in_order() {
+  let rules = Rules::parse(r#"
+    [[redirect]]
+    kind = "pattern"
+    from = "/a/:x"
+    to = "/first/:x"
+
+    [[redirect]]
+    kind = "pattern"
+    from = "/a/*rest"
+    to = "/second/*rest"
+
+    [[redirect]]
+    kind = "prefix"
+    from = "/a/"
+    to = "/short/"
+
+    [[redirect]]
+    kind = "prefix"
+    from = "/a/b/"
+    to = "/long/"
+
+    [[redirect]]
+    from = "/a/b/c"
+    to = "/exact"
+  "#).unwrap();
+
+  let target = |path| rules.resolve(path).map(|(_, to)| to);
+  assert_eq!(target("/a/b/c"), Some("/exact".to_string()));
+  assert_eq!(target("/a/b/d"), Some("/long/d".to_string()));
+  assert_eq!(target("/a/d"), Some("/short/d".to_string()));
+  assert_eq!(target("/b"), None);
+}
+
+#[test]
+fn redirect_cycles_are_rejected() {
+  let cycle = Rules::parse(r#"
+    [[redirect]]
+    from = "/a"
+    to = "/b"
+
+    [[redirect]]
+    kind = "prefix"
+    from = "/b"
+    to = "/c"
+
+    [[redirect]]
+    kind = "pattern"
+    from = "/:page"
+    to = "/a"
+  "#);
+  assert!(cycle.unwrap_err().contains("cycle"));
+
+  let loop_to_self = Rules::parse(r#"
+    [[redirect]]
+    kind = "prefix"
+    from = "/docs/"
+    to = "/docs/v2/"
+  "#);
+  assert!(loop_to_self.is_err());
+
+  // A cycle only some paths take is still one.
+  let crossed_prefixes = Rules::parse(r#"
+    [[redirect]]
+    kind = "prefix"
+    from = "/a/"
+    to = "/b/"
+
+    [[redirect]]
+    kind = "prefix"
+    from = "/b/x"
+    to = "/a/x"
+  "#);
+  assert!(crossed_prefixes.unwrap_err().contains("cycle"));
+
+  let through_a_pattern = Rules::parse(r#"
+    [[redirect]]
+    kind = "pattern"
+    from = "/old/:slug"
+    to = "/new/:slug"
+
+    [[redirect]]
+    kind = "prefix"
+    from = "/new/"
+    to = "/old/"
+  "#);
+  assert!(through_a_pattern.unwrap_err().contains("cycle"));
+
+  // Rules that can't lead into each other are fine.
+  let chain = Rules::parse(r#"
+    [[redirect]]
+    kind = "prefix"
+    from = "/a/"
+    to = "/b/"
+
+    [[redirect]]
+    kind = "pattern"
+    from = "/b/:page"
+    to = "/c/:page"
+  "#);
+  assert!(chain.is_ok());
+}
+
+#[test]
+fn invalid_redirect_rules_are_rejected() {
+  let invalid = &[
+    r#"[[redirect]]
+    from = "/a"
+    to = "/b"
+    status = 307"#,
+    r#"[[redirect]]
+    kind = "pattern"
+    from = "/a/:x"
+    to = "/b/:y""#,
+    r#"[[redirect]]
+    from = "/a"
+    to = "https://example.com/"
+    alias = true"#,
+    r#"[[redirect]]
+    from = "a"
+    to = "/b""#,
+  ];
+
+  for config in invalid {
+    assert!(Rules::parse(config).is_err(), "{}", config);
+  }
+}
+
+#[test]
+fn broken_templates_fail_to_load() {
+  let config = SiteConfig::load().unwrap();
+  assert!(Templates::load(&config.paths.templates, &config, catalogs(&config)).is_ok());
+
+  let dir = env::temp_dir().join("homupage-broken-templates");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("index.html"), "{% extends \"missing.html\" %}").unwrap();
+
+  assert!(Templates::load(&dir, &config, catalogs(&config)).is_err());
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+  pairs
+    .iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect()
+}
+
+#[test]
+fn environment_overrides_site_settings() {
+  let content = fs::read_to_string(config::DEFAULT_FILE).unwrap();
+  let config = SiteConfig::parse(&content, vars(&[
+    ("HOMUPAGE_SITE_URL", "http://localhost:8000/"),
+    ("HOMUPAGE_SITE_SOCIAL_GITHUB", "https://github.com/example"),
+    ("HOMUPAGE_ANIMATION_WINDOW_LINES", "20"),
+    ("HOMUPAGE_ANIMATION_CODE_ORIGIN", "[1.0, 2.5]"),
+    ("PATH", "/usr/bin"),
+  ])).unwrap();
+
+  assert_eq!(config.site.url, "http://localhost:8000");
+  assert_eq!(config.site.social["github"], "https://github.com/example");
+  assert_eq!(config.animation.window_lines, 20);
+  assert_eq!(config.animation.code_origin, [1.0, 2.5]);
+}
+
+#[test]
+fn invalid_site_settings_are_rejected() {
+  let content = fs::read_to_string(config::DEFAULT_FILE).unwrap();
+  let invalid = &[
+    ("HOMUPAGE_SITE_COLOUR", "blue"),
+    ("HOMUPAGE_SITE_URL", "arcrose.me"),
+    ("HOMUPAGE_ANIMATION_WINDOW_LINES", "0"),
+    ("HOMUPAGE_ANIMATION_STEP_MILLIS", "fast"),
+    ("HOMUPAGE_PATHS_SCRIPTS", "./writing"),
+    ("HOMUPAGE_PATHS_STYLES", "./templates"),
+    ("HOMUPAGE_THEME_DARK", "sepia"),
+    ("HOMUPAGE_THEMES_RETRO_ACCENT", "yellow; }"),
+    ("HOMUPAGE_THEMES_LIGHT_BODY_FONT", "Comic Sans"),
+    ("HOMUPAGE_THEMES_LIGHT_ANIMATION_SPEED", "0.0"),
+  ];
+
+  for (name, value) in invalid {
+    let err = SiteConfig::parse(&content, vars(&[(name, value)])).unwrap_err();
+    assert!(!err.is_empty(), "{}", name);
+  }
+}
+
+#[test]
+fn resume_formats_are_negotiated() {
+  let client = client();
+  let negotiated = &[
+    ("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8", "text/html", "<h1"),
+    ("*/*", "text/html", "<h1"),
+    ("text/plain", "text/plain", "EXPERIENCE IN INDUSTRY"),
+    ("text/*;q=0.5, text/markdown", "text/markdown", "## Experience in Industry"),
+    ("application/json, text/html;q=0.1", "application/json", "\"basics\""),
+    ("image/png", "text/html", "<h1"),
+    ("application/pdf", "application/pdf", "%PDF-"),
+  ];
+
+  for (accept, content_type, expected) in negotiated {
+    let mut response = client
+      .get("/resume")
+      .header(Header::new("Accept", *accept))
+      .dispatch();
+    assert!(header(&response, "Content-Type").starts_with(content_type), "{}", accept);
+    assert!(header(&response, "Vary").contains("Accept"));
+    let body = String::from_utf8_lossy(&response.body_bytes().unwrap()).into_owned();
+    assert!(body.contains(expected), "{}", accept);
+  }
+
+  for (url, content_type) in &[("/resume.txt", "text/plain"), ("/resume.md", "text/markdown"), ("/resume.json", "application/json"), ("/resume.pdf", "application/pdf")] {
+    let response = client.get(*url).dispatch();
+    assert!(header(&response, "Content-Type").starts_with(content_type), "{}", url);
+  }
+}
+
+#[test]
+fn resume_dates_are_checked() {
+  assert_eq!(resume::format_date("2011-09"), Some("Sept. 2011".to_string()));
+  assert_eq!(resume::format_date("2014-05-20"), Some("May 2014".to_string()));
+  assert_eq!(resume::format_date("2015"), Some("2015".to_string()));
+  assert_eq!(resume::format_date("2015-13"), None);
+  assert_eq!(resume::format_date("2015-02-30"), None);
+  assert_eq!(resume::format_date("Dec. 2015"), None);
+
+  let resume = |start, end| format!(
+    r#"{{"basics": {{"name": "A"}}, "work": [{{"name": "B", "position": "C", "startDate": "{}", "endDate": "{}"}}]}}"#,
+    start,
+    end);
+  assert!(Resume::parse(&resume("2015-01", "2015-12")).is_ok());
+  assert!(Resume::parse(&resume("2015-12", "2015-01")).is_err());
+  assert!(Resume::parse(&resume("December 2015", "2016")).is_err());
+}
+
+#[test]
+fn resume_pdf_has_links() {
+  let resume = Resume::parse(
+    r#"{"basics": {"name": "A", "url": "https://example.com", "email": "a@example.com"}}"#).unwrap();
+
+  let pdf = String::from_utf8_lossy(&resume::pdf::render(&resume).unwrap()).into_owned();
+  assert!(pdf.starts_with("%PDF-"));
+  assert!(pdf.contains("/URI(https://example.com)"));
+  assert!(pdf.contains("/URI(mailto:a@example.com)"));
+}
+
+#[test]
+fn contact_card_is_a_valid_vcard() {
+  let client = client();
+  let mut response = client.get("/contact.vcf").dispatch();
+  assert!(header(&response, "Content-Type").starts_with("text/vcard"));
+
+  let card = response.body_string().unwrap();
+  assert!(card.starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\n"));
+  assert!(card.ends_with("END:VCARD\r\n"));
+  assert!(card.contains("\r\nFN:Emma Rose\r\n"));
+  assert!(card.contains("\r\nN:Rose;Emma;;;\r\n"));
+  assert!(card.lines().all(|line| line.len() <= 75));
+
+  let resume = Resume::parse(r#"{"basics": {"name": "Ada King", "summary": "Counts, poems; engines.\nAnd more, written out at some great length so that the line has to fold — twice even, if it is long enough to."}}"#).unwrap();
+  let config = SiteConfig::load().unwrap();
+  let card = Contact::new(&config.site, &resume).to_vcard();
+  assert!(card.contains(r"NOTE:Counts\, poems\; engines.\nAnd more"));
+  assert!(card.contains("\r\n "));
+  assert!(card.split("\r\n").all(|line| line.len() <= 75));
+  // Social links from site.toml fill in for profiles the resume doesn't list.
+  assert!(card.contains("X-SOCIALPROFILE;TYPE=github:https://github.com/arcrose"));
+}
+
+#[test]
+fn index_page_has_an_h_card_and_json_ld() {
+  let client = client();
+  let page = client.get("/").dispatch().body_string().unwrap();
+
+  assert!(page.contains(r#"class="h-card""#));
+  assert!(page.contains(r#"<p id="name" class="p-name"><span class="p-given-name">Emma</span> <span class="p-family-name">Rose</span></p>"#));
+  assert!(page.contains(r#"<p><span class="p-job-title">Security Engineer @ Mozilla</span>;</p>"#));
+  assert!(!page.contains("<data "));
+  assert!(page.contains(r#"<link class="u-url" rel="me" href="https://github.com/arcrose" />"#));
+
+  let start = page.find(r#"<script type="application/ld+json">"#).unwrap();
+  let json = &page[start..];
+  let json = &json[json.find('>').unwrap() + 1..json.find("</script>").unwrap()];
+  let person = serde_json::from_str::<serde_json::Value>(json).unwrap();
+  assert_eq!(person["@type"], "Person");
+  assert_eq!(person["name"], "Emma Rose");
+  assert_eq!(person["sameAs"][0], "https://github.com/arcrose");
+}
+
+// The source of a snippet, looked up in the client's state.
+fn snippet<'a>(client: &'a Client, language: &str, file: &str) -> &'a code::Source {
+  client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap()
+    .iter()
+    .find(|dir| dir.language_name == language)
+    .and_then(|dir| dir.source_files.iter().find(|source| source.file_name == file))
+    .unwrap()
+}
+
+#[test]
+fn source_lines_are_numbered_from_one_with_indent_guides() {
+  let client = client();
+  let source = snippet(&client, "rust", "arcrose-clink-src-links-tags.rs");
+  let page = client.get("/code/rust/arcrose-clink-src-links-tags.rs").dispatch().body_string().unwrap();
+
+  assert!(page.contains(r##"<tr id="L1"><td class="line-number"><a href="#L1">1</a></td>"##));
+  assert!(!page.contains(r#"<tr id="L0">"#));
+  assert_eq!(page.matches("<tr id=").count(), source.lines_of_code.len());
+
+  let line = source.lines_of_code.iter().find(|line| line.indent > 1).unwrap();
+  let row = format!(
+    r##"<tr id="L{0}"><td class="line-number"><a href="#L{0}">{0}</a></td><td class="line">{1}"##,
+    line.number + 1,
+    r#"<span class="indent"></span>"#.repeat(line.indent as usize));
+  assert!(page.contains(&row), "{}", row);
+  assert!(!page.contains(&format!(r#"{}<span class="indent">"#, row)));
+}
+
+#[test]
+fn sources_show_where_they_were_taken_from() {
+  let client = client();
+  let page = client.get("/code/rust/arcrose-clink-src-links-tags.rs").dispatch().body_string().unwrap();
+
+  assert!(page.contains("<h1>tags.rs</h1>"));
+  assert!(page.contains(r#"<code>src/links/tags.rs</code> from <a href="https://github.com/arcrose/clink">arcrose/clink</a> &middot; Rust &middot; "#));
+}
+
+#[test]
+fn code_index_groups_sources_by_language() {
+  let client = client();
+  let page = client.get("/code").dispatch().body_string().unwrap();
+
+  for dir in client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap() {
+    let heading = format!("<h2>{}</h2>", highlight::language_title(&dir.language_name));
+    let section = page.split(heading.as_str()).nth(1).unwrap_or_else(|| panic!("{} is missing", heading));
+    let section = section.split("<h2>").next().unwrap();
+    for source in &dir.source_files {
+      let link = format!(r#"<a href="/code/{}/{}">"#, dir.language_name, source.file_name);
+      assert!(section.contains(&link), "{} is not under {}", link, heading);
+    }
+  }
+}
+
+#[test]
+fn unknown_sources_are_not_found() {
+  let client = client();
+
+  for path in &["/code/cobol/arcrose-clink-src-links-tags.rs", "/code/rust/no-such-file.rs", "/code/go/arcrose-clink-src-links-tags.rs"] {
+    assert_eq!(client.get(*path).dispatch().status(), Status::NotFound, "{}", path);
+  }
+}
+
+#[test]
+fn feeds_link_every_sample_by_its_absolute_url() {
+  let client = client();
+  let samples = client.rocket().state::<Vec<writing::Sample>>().unwrap();
+
+  let mut response = client.get("/feed.atom").dispatch();
+  assert_eq!(response.content_type(), Some(ContentType::new("application", "atom+xml")));
+  let atom = response.body_string().unwrap();
+  let mut response = client.get("/feed.rss").dispatch();
+  assert_eq!(response.content_type(), Some(ContentType::new("application", "rss+xml")));
+  let rss = response.body_string().unwrap();
+
+  assert_eq!(atom.matches("<entry>").count(), samples.len());
+  assert_eq!(rss.matches("<item>").count(), samples.len());
+  for sample in samples {
+    let url = format!("https://arcrose.me/writing/{}", sample.slug);
+    assert!(atom.contains(&format!("<link href=\"{0}\"/>\n    <id>{0}</id>", url)), "{}", url);
+    assert!(rss.contains(&format!("<link>{0}</link>\n      <guid isPermaLink=\"true\">{0}</guid>", url)), "{}", url);
+  }
+}
+
+#[test]
+fn feed_entries_are_dated_and_summarised() {
+  let config = SiteConfig::load().unwrap();
+  let dir = env::temp_dir().join("homupage-feed-samples");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("dated.txt"), "---\ndate: 2016-09-12\n---\nFish & <chips> for \"tea\".\n").unwrap();
+  fs::write(dir.join("undated.txt"), "No date.\n").unwrap();
+  let modified = fs::metadata(dir.join("undated.txt")).unwrap().modified().unwrap();
+  let samples = writing::collect(&dir, &catalogs(&config)).unwrap();
+  fs::remove_dir_all(&dir).unwrap();
+
+  let atom = feed::atom(&config.site, &samples);
+  let rss = feed::rss(&config.site, &samples);
+  let modified = DateTime::<Utc>::from(modified);
+
+  assert!(atom.contains("<updated>2016-09-12T00:00:00Z</updated>"));
+  assert!(rss.contains("<pubDate>Mon, 12 Sep 2016 00:00:00 +0000</pubDate>"));
+  assert!(atom.contains(&format!("<updated>{}</updated>", modified.to_rfc3339_opts(SecondsFormat::Secs, true))));
+  assert!(rss.contains(&format!("<pubDate>{}</pubDate>", modified.to_rfc2822())));
+
+  let summary = "Fish &amp; &lt;chips&gt; for &quot;tea&quot;.";
+  assert!(atom.contains(&format!("<summary>{}</summary>", summary)), "{}", atom);
+  assert!(rss.contains(&format!("<description>{}</description>", summary)), "{}", rss);
+}
+
+#[test]
+fn export_writes_both_feeds() {
+  let client = client();
+  let dir = env::temp_dir().join("homupage-export");
+  export::export(&dir, client.rocket()).unwrap();
+  let atom = fs::read_to_string(dir.join("feed.atom")).unwrap();
+  let rss = fs::read_to_string(dir.join("feed.rss")).unwrap();
+  fs::remove_dir_all(&dir).unwrap();
+
+  assert_eq!(atom, client.get("/feed.atom").dispatch().body_string().unwrap());
+  assert_eq!(rss, client.get("/feed.rss").dispatch().body_string().unwrap());
+}
+
+#[test]
+fn sitemap_lists_pages_with_their_last_modified_dates() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let source_dir = &client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap()[0];
+  let mut response = client.get("/sitemap.xml").dispatch();
+  assert_eq!(response.content_type(), Some(ContentType::XML));
+  let sitemap = response.body_string().unwrap();
+
+  for path in &["/", "/resume", "/writing", "/code"] {
+    assert!(sitemap.contains(&format!("<loc>https://arcrose.me{}</loc>", path)), "{} is missing", path);
+  }
+  assert!(sitemap.contains(&format!(
+    "<loc>https://arcrose.me/writing/{}</loc>\n    <lastmod>{}</lastmod>",
+    sample.slug,
+    sample.updated.to_rfc3339_opts(SecondsFormat::Secs, true))));
+  assert!(sitemap.contains(&format!(
+    "<loc>https://arcrose.me/code/{}/{}</loc>",
+    source_dir.language_name,
+    source_dir.source_files[0].file_name)));
+
+  // Disallowed paths and anything that isn't a page stay out.
+  for path in &["/api/", "/search", "/feed.atom", "/resume.pdf", "/sitemap.xml"] {
+    assert!(!sitemap.contains(&format!("https://arcrose.me{}<", path)), "{} is listed", path);
+  }
+}
+
+#[test]
+fn robots_txt_points_at_the_sitemap() {
+  let client = client();
+  let robots = client.get("/robots.txt").dispatch().body_string().unwrap();
+
+  assert_eq!(robots, "User-agent: *\nDisallow: /api/\nDisallow: /search\n\nSitemap: https://arcrose.me/sitemap.xml\n");
+}
+
+#[test]
+fn pages_link_to_their_canonical_url() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+
+  for path in &["/".to_string(), "/resume".to_string(), "/code".to_string(), format!("/writing/{}", sample.slug)] {
+    let page = client.get(path.as_str()).dispatch().body_string().unwrap();
+    assert!(page.contains(&format!(r#"<link rel="canonical" href="https://arcrose.me{}" />"#, path)), "{} has no canonical link", path);
+  }
+  let missing = client.get("/no/such/page").dispatch().body_string().unwrap();
+  assert!(!missing.contains(r#"rel="canonical""#));
+}
+
+#[test]
+fn shared_pages_have_open_graph_and_twitter_metadata() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let page = client.get(format!("/writing/{}", sample.slug)).dispatch().body_string().unwrap();
+
+  assert!(page.contains(r#"<meta property="og:type" content="article">"#));
+  assert!(page.contains(&format!(r#"<meta property="og:title" content="{}">"#, sample.title)));
+  assert!(page.contains(&format!(r#"<meta property="og:image" content="https://arcrose.me/preview/writing/{}.png">"#, sample.slug)));
+  assert!(page.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+
+  let index = client.get("/").dispatch().body_string().unwrap();
+  assert!(index.contains(r#"<meta property="og:image" content="https://arcrose.me/preview/index.png">"#));
+}
+
+#[test]
+fn previews_are_rendered_as_png() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let mut response = client.get(format!("/preview/writing/{}.png", sample.slug)).dispatch();
+  assert_eq!(response.status(), Status::Ok);
+  assert_eq!(response.content_type(), Some(ContentType::PNG));
+
+  let png = response.body_bytes().unwrap();
+  assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+  // Width and height are the first fields of the IHDR chunk.
+  assert_eq!(&png[16..24], &[0, 0, 0x04, 0xb0, 0, 0, 0x02, 0x76]);
+
+  assert_eq!(client.get("/preview/writing/no-such-sample.png").dispatch().status(), Status::NotFound);
+  assert_eq!(client.get("/preview/index.jpg").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn palettes_are_taken_from_themes() {
+  let mut config = SiteConfig::load().unwrap();
+  let light = config.theme.light.clone();
+  let palette = raster::Palette::from_theme(&config.themes[&light]);
+  assert_eq!(palette.code_fill, [0x17, 0x54, 0x6d]);
+
+  // Short colours are widened, and alpha is left out.
+  let theme = config.themes.get_mut(&light).unwrap();
+  theme.background = "#fe0".to_string();
+  theme.surface = "#ffffff80".to_string();
+  let palette = raster::Palette::from_theme(theme);
+  assert_eq!(palette.background, [0xff, 0xee, 0x00]);
+  assert_eq!(palette.writing_fill, [0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn index_page_draws_the_first_frame_of_the_segments() {
+  let client = client();
+  let config = client.rocket().state::<SiteConfig>().unwrap();
+  let source_dir = &client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap()[0];
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let page = client.get("/").dispatch().body_string().unwrap();
+
+  assert!(page.contains(&format!(r#"data-language="{}""#, source_dir.language_name)));
+  assert!(page.contains(&format!(r#"data-file="{}""#, source_dir.source_files[0].file_name)));
+  assert!(page.contains(&format!(r#"data-slug="{}""#, sample.slug)));
+
+  let window = config.animation.window_lines as usize;
+  let code_lines = source_dir.source_files[0].lines_of_code.len().min(window);
+  let writing_lines = sample.lines.len().min(window);
+  assert_eq!(page.matches(r#"class="code""#).count(), code_lines);
+  assert_eq!(page.matches(r#"class="writing""#).count(), writing_lines);
+
+  // The first lines sit at the origins `js/main.js` draws from.
+  let first = &source_dir.source_files[0].lines_of_code[0];
+  let [x, y] = config.animation.code_origin;
+  let x = first.indent as f64 * config.animation.line_delta[0] + x;
+  assert!(page.contains(&format!(r#"<text x="{}" y="{}" class="code" aria-hidden="true">"#, x, y)));
+  let [x, y] = config.animation.writing_origin;
+  assert!(page.contains(&format!(r#"<text x="{}" y="{}" class="writing" aria-hidden="true">"#, x, y)));
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+  args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn animation_settings_come_from_the_command_line() {
+  let config = SiteConfig::load().unwrap();
+  let settings = scroll::Settings::from_args(
+    &args(&["scroll.svg", "--fps", "25", "--seconds", "12", "--lines", "8", "--size", "640x360"]),
+    &config.animation).unwrap();
+  assert_eq!(settings, scroll::Settings {
+    out_file: "scroll.svg".to_string(),
+    fps: 25,
+    seconds: 12,
+    window_lines: 8,
+    width: 640,
+    height: 360,
+  });
+
+  let defaults = scroll::Settings::from_args(&args(&["scroll.png"]), &config.animation).unwrap();
+  assert_eq!(defaults.window_lines, config.animation.window_lines);
+
+  for bad in &[&["scroll.gif"][..], &["scroll.svg", "--fps", "0"], &["scroll.svg", "--size", "big"], &["scroll.svg", "--fps"]] {
+    assert!(scroll::Settings::from_args(&args(bad), &config.animation).is_err(), "{:?} was accepted", bad);
+  }
+}
+
+#[test]
+fn animations_follow_the_script_schedule() {
+  let config = SiteConfig::load().unwrap();
+  let source_dirs = code::analyze(&config.paths.snippets).unwrap();
+  let samples = writing::collect(&config.paths.writing, &catalogs(&config)).unwrap();
+  let settings = scroll::Settings::from_args(&args(&["scroll.svg", "--fps", "50", "--seconds", "3"]), &config.animation).unwrap();
+  let frames = scroll::frames(&settings, &config.animation, &samples, &source_dirs);
+
+  // The first frame is shown for a tick, and every later one for two.
+  let step = config.animation.step_millis;
+  assert_eq!(frames[0].1, step);
+  assert!(frames[1..frames.len() - 1].iter().all(|(_, millis)| *millis == 2 * step));
+  assert_eq!(frames.iter().map(|(_, millis)| millis).sum::<u32>(), 3000);
+
+  // Lines scroll up the code segment one at a time.
+  let lines = &source_dirs[0].source_files[0].lines_of_code;
+  assert_eq!(frames[0].0.code[0].text, lines[0].code);
+  assert_eq!(frames[1].0.code[0].text, lines[1].code);
+  assert_eq!(frames[1].0.code[0].y, config.animation.code_origin[1]);
+
+  let svg = scroll::to_svg(&settings, &config, &frames).unwrap();
+  assert_eq!(svg.matches("<animate ").count(), 2 * frames.len());
+  // Its fonts are embedded rather than left to whatever's installed.
+  let theme = &config.themes[&config.theme.light];
+  assert!(svg.contains(&format!(r#"@font-face {{ font-family: "{}"; src: url("data:font/woff2;base64,"#, theme.mono_font)));
+  assert!(svg.contains(&format!(r#"font-family: "{}", sans-serif"#, theme.body_font)));
+  // And it's drawn in the light theme's colours.
+  assert!(svg.contains(&format!(r#"fill="{}""#, theme.background)));
+  assert!(svg.contains(&format!(r#"fill="{}""#, theme.accent)));
+  assert!(svg.contains(&format!(r#"keyTimes="0;0;{}""#, step as f64 / 3000.0)));
+}
+
+#[test]
+fn reduced_motion_serves_the_first_frame_without_scripts() {
+  let client = client();
+
+  // Without a choice, it's left to the script.
+  let default = client.get("/").dispatch().body_string().unwrap();
+  assert!(default.contains("/js/main") && default.contains(r#"data-motion="default""#));
+
+  let mut response = client.get("/?motion=reduce").dispatch();
+  assert_eq!(header(&response, "Vary"), "Cookie, Sec-CH-Prefers-Reduced-Motion, Accept-Language, Accept-Encoding");
+  assert!(header(&response, "Set-Cookie").starts_with("motion=reduce;"));
+  let page = response.body_string().unwrap();
+  assert!(!page.contains("/js/main"));
+  assert!(page.contains(r#"class="code" aria-hidden="true""#));
+  assert!(page.contains(r#"<a href="/?motion=full">Animate</a>"#));
+
+  let remembered = client.get("/").header(Header::new("Cookie", "motion=reduce")).dispatch().body_string().unwrap();
+  assert!(!remembered.contains("/js/main"));
+  let hinted = client.get("/").header(Header::new("Sec-CH-Prefers-Reduced-Motion", "reduce")).dispatch().body_string().unwrap();
+  assert!(!hinted.contains("/js/main"));
+
+  // Asking for motion outranks the hint.
+  let chosen = client
+    .get("/?motion=full")
+    .header(Header::new("Sec-CH-Prefers-Reduced-Motion", "reduce"))
+    .dispatch()
+    .body_string()
+    .unwrap();
+  assert!(chosen.contains("/js/main") && chosen.contains(r#"data-motion="full""#));
+}
+
+#[test]
+fn negotiated_responses_add_to_vary() {
+  let client = Client::new(rocket::ignite()).unwrap();
+  let request = client.get("/");
+  let compressed = || Response::build().raw_header("Vary", "Accept-Encoding").finalize();
+
+  let response = ByMotion(compressed()).respond_to(request.inner()).unwrap();
+  assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding, Cookie, Sec-CH-Prefers-Reduced-Motion"));
+  let response = Negotiated(compressed()).respond_to(request.inner()).unwrap();
+  assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding, Accept"));
+}
+
+#[test]
+fn segments_are_described_for_screen_readers() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[0];
+  let page = client.get("/").dispatch().body_string().unwrap();
+
+  assert!(page.contains(r#"aria-describedby="code-segment-description""#));
+  assert!(page.contains("starting with Authentication.elm from arcrose/AsQ."));
+  assert!(page.contains(&format!("starting with “{}”.", sample.title)));
+  assert!(page.contains(r#"<a id="transcript-link" href="/transcript">"#));
+}
+
+#[test]
+fn transcript_shows_what_the_animation_is_showing() {
+  let client = client();
+  let sample = &client.rocket().state::<Vec<writing::Sample>>().unwrap()[1];
+  let source_dir = &client.rocket().state::<Vec<code::SourceCodeDirectory>>().unwrap()[0];
+  let source = &source_dir.source_files[1];
+
+  let first = client.get("/transcript").dispatch().body_string().unwrap();
+  assert!(first.contains(r#"<a href="/code/elm/arcrose-AsQ-client-Authentication.elm">Authentication.elm from arcrose/AsQ</a>"#));
+  assert!(first.contains(r#"<td class="line-number">1</td>"#));
+
+  let url = format!(
+    "/transcript?language={}&file={}&code_line=5&slug={}&writing_line=0",
+    source_dir.language_name, source.file_name, sample.slug);
+  let later = client.get(url).dispatch().body_string().unwrap();
+  assert!(later.contains(&format!(r#"<a href="/writing/{}">{}</a>"#, sample.slug, sample.title)));
+  assert!(later.contains(&format!(r#"<a href="/code/{}/{}">"#, source_dir.language_name, source.file_name)));
+  assert!(later.contains(r#"<td class="line-number">6</td>"#));
+  assert!(!later.contains(r#"<td class="line-number">5</td>"#));
+
+  assert_eq!(client.get("/transcript?slug=no-such-sample").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn languages_are_negotiated_from_accept_language() {
+  let catalogs = catalogs(&SiteConfig::load().unwrap());
+
+  assert_eq!(catalogs.negotiate("ja"), Some("ja"));
+  assert_eq!(catalogs.negotiate("ja-JP,ja;q=0.9"), Some("ja"));
+  assert_eq!(catalogs.negotiate("fr, ja;q=0.8, en;q=0.5"), Some("ja"));
+  assert_eq!(catalogs.negotiate("en;q=0.5, ja;q=0.5"), Some("en"));
+  assert_eq!(catalogs.negotiate("ja;q=0, en"), Some("en"));
+  assert_eq!(catalogs.negotiate("fr, *"), None);
+
+  // Catalogs fall back to the default language's messages.
+  assert_eq!(catalogs.message("ja", "nav.writing"), "文章");
+  assert_eq!(catalogs.message("fr", "nav.writing"), "Writing");
+  assert_eq!(catalogs.message("ja", "no.such.message"), "no.such.message");
+}
+
+#[test]
+fn pages_are_served_in_the_chosen_language() {
+  let client = client();
+
+  let mut response = client.get("/writing").header(Header::new("Accept-Language", "ja-JP,ja;q=0.9")).dispatch();
+  assert!(header(&response, "Vary").contains("Accept-Language"));
+  let page = response.body_string().unwrap();
+  assert!(page.contains(r#"<html lang="ja">"#));
+  assert!(page.contains("<h1>文章</h1>"));
+  assert!(page.contains(r#"<link rel="canonical" href="https://arcrose.me/writing?lang=ja" />"#));
+  assert!(page.contains(r#"<link rel="alternate" hreflang="en" href="https://arcrose.me/writing" />"#));
+  assert!(page.contains(r#"<link rel="alternate" hreflang="ja" href="https://arcrose.me/writing?lang=ja" />"#));
+  assert!(page.contains(r#"<link rel="alternate" hreflang="x-default" href="https://arcrose.me/writing" />"#));
+
+  // Choosing a language outranks the header, and is remembered.
+  let response = client.get("/code?lang=en").header(Header::new("Accept-Language", "ja")).dispatch();
+  assert!(header(&response, "Set-Cookie").starts_with("lang=en;"));
+  let remembered = client.get("/code").header(Header::new("Accept-Language", "ja")).dispatch().body_string().unwrap();
+  assert!(remembered.contains(r#"<html lang="en">"#));
+
+  let missing = client.get("/no/such/page?lang=ja").dispatch().body_string().unwrap();
+  assert!(missing.contains("<code>/no/such/page</code> には何もありません"));
+}
+
+#[test]
+fn translated_samples_replace_the_original_in_their_language() {
+  let client = client();
+  let samples = client.rocket().state::<Vec<writing::Sample>>().unwrap();
+  let sample = samples.iter().find(|sample| !sample.translations.is_empty()).unwrap();
+  let translation = sample.in_language("ja");
+  assert_eq!(translation.slug, sample.slug);
+  assert_ne!(translation.lines[0].text, sample.lines[0].text);
+  assert!(samples.iter().all(|sample| sample.language.is_none()));
+
+  let article = client.get(format!("/writing/{}?lang=ja", sample.slug)).dispatch().body_string().unwrap();
+  assert!(article.contains(r#"<article lang="ja">"#));
+  assert!(article.contains(&translation.title));
+
+  // The animation starts on the translation, and the script has the rest.
+  let index = client.get("/?lang=ja").dispatch().body_string().unwrap();
+  assert!(index.contains(&translation.lines[0].text));
+  assert!(index.contains("/js/writing_samples.ja"));
+
+  let mut config = SiteConfig::load().unwrap();
+  let dir = env::temp_dir().join("homupage-data-scripts");
+  fs::create_dir_all(&dir).unwrap();
+  config.paths.scripts = dir.to_string_lossy().to_string();
+  export::write_data_scripts(&config, &catalogs(&config), samples, &[]).unwrap();
+  let script = fs::read_to_string(dir.join("writing_samples.ja.js")).unwrap();
+  assert!(script.contains(&translation.lines[0].text));
+  let script = fs::read_to_string(dir.join("writing_samples.js")).unwrap();
+  assert!(!script.contains(&translation.lines[0].text));
+  fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_catalog_languages_mark_translations() {
+  let dir = env::temp_dir().join("homupage-dotted-samples");
+  fs::create_dir_all(&dir).unwrap();
+  fs::write(dir.join("notes.old.txt"), "Old notes.\n").unwrap();
+  fs::write(dir.join("dc416.ctf.txt"), "A write-up.\n").unwrap();
+  fs::write(dir.join("dc416.ctf.ja.txt"), "解説。\n").unwrap();
+  let samples = writing::collect(&dir, &catalogs(&SiteConfig::load().unwrap())).unwrap();
+  fs::remove_dir_all(&dir).unwrap();
+
+  let slugs = samples.iter().map(|sample| sample.slug.as_str()).collect::<Vec<&str>>();
+  assert_eq!(slugs, vec!["dc416-ctf", "notes-old"]);
+  assert_eq!(samples[0].translations.len(), 1);
+  assert_eq!(samples[0].in_language("ja").lines[0].text, "解説。");
+  assert!(samples[1].translations.is_empty());
+}
+
+#[test]
+fn articles_link_to_their_neighbours_in_a_series() {
+  let client = client();
+  let article = |slug: &str| client.get(format!("/writing/{}", slug)).dispatch().body_string().unwrap();
+
+  let first = article("fp-and-nlp");
+  assert!(!first.contains(r#"rel="prev""#));
+  assert!(first.contains(r#"<a rel="next" href="/writing/fp-and-nlp2">"#));
+
+  let middle = article("fp-and-nlp2");
+  assert!(middle.contains(r#"<a rel="prev" href="/writing/fp-and-nlp">"#));
+  assert!(middle.contains(r#"<a rel="next" href="/writing/fp-and-nlp3">"#));
+
+  // The next sample along belongs to another series.
+  let last = article("error-handling-rust2");
+  assert!(last.contains(r#"<a rel="prev" href="/writing/error-handling-rust">"#));
+  assert!(!last.contains(r#"rel="next""#));
+}
+
+#[test]
+fn unknown_articles_are_not_found() {
+  let client = client();
+  assert_eq!(client.get("/writing/no-such-sample").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn samples_cannot_share_a_slug() {
+  let catalogs = catalogs(&SiteConfig::load().unwrap());
+  let clashes: &[&[(&str, &str)]] = &[
+    &[("on-rust.txt", "Rust.\n"), ("On Rust.txt", "Rust again.\n")],
+    &[("on-rust.txt", "Rust.\n"), ("rust.txt", "---\nslug: on-rust\n---\nRust again.\n")],
+  ];
+
+  for (i, files) in clashes.iter().enumerate() {
+    let dir = env::temp_dir().join(format!("homupage-slug-clash-{}", i));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, content) in *files {
+      fs::write(dir.join(name), content).unwrap();
+    }
+    let err = writing::collect(&dir, &catalogs).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let message = err.to_string();
+    assert!(files.iter().all(|(name, _)| message.contains(name)), "{}", message);
+    assert!(message.contains("`on-rust`"), "{}", message);
+  }
+}
+
+#[test]
+fn japanese_samples_are_wrapped_and_joined_without_spaces() {
+  let dir = env::temp_dir().join("homupage-japanese-samples");
+  fs::create_dir_all(&dir).unwrap();
+  let paragraph = "吾輩《わがはい》は猫である。名前はまだ無い。「どこで生れたかとんと見当《けんとう》がつかぬ」。".repeat(5);
+  let body = format!("　{}\n　｜東京タワー《とうきょうタワー》に行った。\n", paragraph);
+  fs::write(dir.join("neko.txt"), body).unwrap();
+  let samples = writing::collect(&dir, &catalogs(&SiteConfig::load().unwrap())).unwrap();
+  fs::remove_dir_all(&dir).unwrap();
+  let sample = &samples[0];
+
+  // Long lines are wrapped by width, never before closing brackets or small
+  // kana, and never through a word with ruby over it.
+  assert!(sample.lines.len() > 2);
+  for line in &sample.lines {
+    assert!(line.text.chars().count() <= 50);
+    assert!(!line.text.starts_with(|c| "」。っ".contains(c)));
+    for ruby in &line.ruby {
+      assert!(ruby.end <= line.text.chars().count());
+    }
+  }
+  assert_eq!(sample.lines[0].ruby[0], writing::Ruby { start: 1, end: 3, text: "わがはい".to_string() });
+  assert!(sample.lines[0].text.starts_with("　吾輩は猫である。"));
+
+  let paragraphs = sample.paragraphs();
+  assert_eq!(paragraphs.len(), 2);
+  assert!(paragraphs[0].starts_with("吾輩は猫である。名前はまだ無い。"));
+  assert!(!paragraphs[0].contains(' '));
+  assert_eq!(paragraphs[1], "東京タワーに行った。");
+  let annotated = sample.annotated_paragraphs();
+  assert_eq!(annotated[1][0], writing::Run { text: "東京タワー".to_string(), ruby: Some("とうきょうタワー".to_string()) });
+
+  assert_eq!(sample.stats.word_count, 0);
+  assert_eq!(sample.stats.character_count, 159);
+  assert_eq!(sample.stats.sentence_count, 16);
+  assert!(sample.excerpt().ends_with('…'));
+}
+
+#[test]
+fn ruby_is_set_over_japanese_articles() {
+  let client = client();
+  let samples = client.rocket().state::<Vec<writing::Sample>>().unwrap();
+  let sample = samples.iter().find(|sample| !sample.translations.is_empty()).unwrap();
+
+  let article = client.get(format!("/writing/{}?lang=ja", sample.slug)).dispatch().body_string().unwrap();
+  assert!(article.contains("<ruby>躍<rp>(</rp><rt>おど</rt><rp>)</rp></ruby>り出る"));
+  assert!(article.contains("文字</p>"));
+  assert!(!article.contains('《'));
+  let english = client.get(format!("/writing/{}?lang=en", sample.slug)).dispatch().body_string().unwrap();
+  assert!(english.contains(" words</p>"));
+}
+
+#[test]
+fn themes_are_compiled_into_css_variables() {
+  let config = SiteConfig::load().unwrap();
+  let css = themes::stylesheet(&config);
+
+  assert!(css.contains("src: url(\"/fonts/Inconsolata-Regular.woff2\") format(\"woff2\");"));
+  assert!(css.contains(&format!(":root {{\n  --background: {};", config.themes[&config.theme.light].background)));
+  assert!(css.contains(&format!("@media (prefers-color-scheme: dark) {{\n  :root {{\n    --background: {};", config.themes[&config.theme.dark].background)));
+  assert!(css.contains(":root[data-theme=\"retro\"] {"));
+  assert!(css.contains("--animation-speed: 0.5;"));
+
+  // Pages load nothing from elsewhere.
+  let client = client();
+  let page = client.get("/writing").dispatch().body_string().unwrap();
+  assert!(page.contains("/css/themes."));
+  assert!(!page.contains("https://fonts."));
+}
+
+#[test]
+fn chosen_themes_are_applied_and_remembered() {
+  let client = client();
+
+  // The index page has no footer, so it offers the themes itself.
+  let index = client.get("/").dispatch().body_string().unwrap();
+  assert!(index.contains(r#"<a href="/?theme=retro">"#));
+  assert!(index.contains(r#"<a href="/?theme=auto">"#));
+
+  let response = client.get("/?theme=retro").dispatch();
+  assert!(header(&response, "Set-Cookie").starts_with("theme=retro;"));
+  assert!(header(&response, "Vary").contains("Cookie"));
+  let mut remembered = client.get("/writing").dispatch();
+  assert!(remembered.body_string().unwrap().contains(r#"<html data-theme="retro" lang="en">"#));
+
+  let mut unknown = client.get("/writing?theme=sepia").dispatch();
+  assert!(unknown.body_string().unwrap().contains(r#"data-theme="retro""#));
+
+  let mut auto = client.get("/writing?theme=auto").dispatch();
+  assert!(auto.body_string().unwrap().contains(r#"<html lang="en">"#));
+  let mut forgotten = client.get("/code").dispatch();
+  assert!(!forgotten.body_string().unwrap().contains("data-theme"));
+}
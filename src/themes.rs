@@ -0,0 +1,140 @@
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use rocket::{Data, Request, Response, State};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Cookie, SameSite};
+
+use assets;
+use compression;
+use config::{self, SiteConfig, Theme};
+
+
+pub const STYLESHEET: &str = "themes.css";
+const COOKIE: &str = "theme";
+// Asks to follow the system's colour scheme again, forgetting any choice.
+const AUTO: &str = "auto";
+
+// Compile the themes into custom properties on the root element, with the
+// light theme as the default, the dark one for systems that prefer it, and
+// any theme when it's been chosen with `data-theme`. The fonts the themes use
+// are declared for the browser to load from the `fonts` asset root.
+pub fn stylesheet(config: &SiteConfig) -> String {
+  let mut css = String::from("/* Compiled from the themes in the site settings. */\n");
+
+  for (family, font) in &config.fonts {
+    let files = Some((400, &font.regular)).into_iter().chain(font.bold.as_ref().map(|bold| (700, bold)));
+    for (weight, file) in files {
+      writeln!(
+        css,
+        "\n@font-face {{\n  font-family: \"{}\";\n  font-weight: {};\n  font-display: swap;\n  src: url(\"/{}/{}\") format(\"{}\");\n}}",
+        family, weight, assets::FONTS, file, format(file)).unwrap();
+    }
+  }
+
+  let themes = &config.themes;
+  writeln!(css, "\n:root {{\n{}}}", properties(&themes[&config.theme.light], "  ")).unwrap();
+  writeln!(
+    css,
+    "\n@media (prefers-color-scheme: dark) {{\n  :root {{\n{}  }}\n}}",
+    properties(&themes[&config.theme.dark], "    ")).unwrap();
+  for (name, theme) in themes {
+    writeln!(css, "\n:root[data-theme=\"{}\"] {{\n{}}}", name, properties(theme, "  ")).unwrap();
+  }
+
+  css
+}
+
+fn properties(theme: &Theme, indent: &str) -> String {
+  let mut properties = String::new();
+  for (name, value) in &[
+    ("background", theme.background.clone()),
+    ("surface", theme.surface.clone()),
+    ("text", theme.text.clone()),
+    ("muted", theme.muted.clone()),
+    ("accent", theme.accent.clone()),
+    ("code-background", theme.code_background.clone()),
+    ("code-text", theme.code_text.clone()),
+    ("body-font", format!("\"{}\", sans-serif", theme.body_font)),
+    ("mono-font", format!("\"{}\", monospace", theme.mono_font)),
+    ("display-font", format!("\"{}\", monospace", theme.display_font)),
+    ("animation-speed", theme.animation_speed.to_string()),
+  ] {
+    writeln!(properties, "{}--{}: {};", indent, name, value).unwrap();
+  }
+  properties
+}
+
+fn format(file: &str) -> &'static str {
+  match Path::new(file).extension().and_then(|extension| extension.to_str()) {
+    Some("woff2") => "woff2",
+    Some("woff")  => "woff",
+    Some("otf")   => "opentype",
+    _             => "truetype",
+  }
+}
+
+// Write the compiled themes into the styles directory, to be served and
+// fingerprinted with the rest of the assets.
+pub fn write_stylesheet(config: &SiteConfig) -> Result<(), io::Error> {
+  fs::write(Path::new(&config.paths.styles).join(STYLESHEET), stylesheet(config))
+}
+
+// Applies the theme a visitor has chosen to every page. `?theme=retro` chooses
+// one and is remembered in a cookie, and `?theme=auto` goes back to following
+// the system's colour scheme, which the stylesheet does by itself.
+pub struct Themes;
+
+impl Fairing for Themes {
+  fn info(&self) -> Info {
+    Info {
+      name: "Themes",
+      kind: Kind::Request | Kind::Response,
+    }
+  }
+
+  fn on_request(&self, request: &mut Request, _: &Data) {
+    let chosen = match request.get_query_value::<String>(COOKIE).and_then(Result::ok) {
+      Some(chosen) => chosen,
+      None         => return,
+    };
+
+    if chosen == AUTO {
+      request.cookies().remove(Cookie::build(COOKIE, "").path("/").finish());
+    } else if is_theme(request, &chosen) {
+      let cookie = Cookie::build(COOKIE, chosen)
+        .path("/")
+        .same_site(SameSite::Lax)
+        .permanent()
+        .finish();
+      request.cookies().add(cookie);
+    }
+  }
+
+  fn on_response(&self, request: &Request, response: &mut Response) {
+    if response.content_type() != Some(ContentType::HTML) {
+      return;
+    }
+    compression::add_vary(response, "Cookie");
+
+    let theme = match request.cookies().get(COOKIE) {
+      Some(cookie) if is_theme(request, cookie.value()) => cookie.value().to_string(),
+      _                                                  => return,
+    };
+    if let Some(body) = response.body_string() {
+      let themed = body.replacen("<html", &format!("<html data-theme=\"{}\"", theme), 1);
+      response.set_sized_body(Cursor::new(themed));
+    }
+  }
+}
+
+fn is_theme(request: &Request, name: &str) -> bool {
+  config::is_theme_name(name)
+    && request
+      .guard::<State<SiteConfig>>()
+      .succeeded()
+      .map(|config| config.themes.contains_key(name))
+      .unwrap_or(false)
+}
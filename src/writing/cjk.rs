@@ -0,0 +1,253 @@
+use std::ops::Range;
+
+
+// Japanese paragraphs are indented by a full-width space rather than set off
+// by blank lines.
+pub const IDEOGRAPHIC_SPACE: char = '\u{3000}';
+
+// Ruby is written as in Aozora Bunko texts: `漢字《かんじ》` annotates the run
+// of kanji before it, and `｜東京タワー《とうきょうタワー》` marks where a base
+// that isn't all kanji starts.
+const RUBY_START: char = '｜';
+const RUBY_OPEN: char = '《';
+const RUBY_CLOSE: char = '》';
+
+// Sentences in Japanese end with these, without a space after them.
+const FULL_STOPS: &[char] = &['。', '！', '？', '．'];
+
+// A reading annotation over part of a line, by the characters it spans.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Ruby {
+  pub start: usize,
+  pub end: usize,
+  pub text: String,
+}
+
+// How a character behaves at a line break, from the classes in UAX #14,
+// folded together where they behave alike here.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+  Space,
+  // Opening brackets and quotes, which nothing may break after.
+  Open,
+  // Closing brackets and quotes, and the punctuation that follows a word, which
+  // nothing may break before.
+  Close,
+  // Small kana, the prolonged sound mark and iteration marks, which can't
+  // start a line either.
+  NonStarter,
+  Combining,
+  Other,
+}
+
+fn class(c: char) -> Class {
+  match c {
+    c if c.is_whitespace() => Class::Space,
+    '(' | '[' | '{' | '“' | '‘' | '«' | '「' | '『' | '（' | '［' | '｛' | '〔' | '〈' | '《' | '【' | '〖' | '〘' | '〚' | '｟' | '｢' => Class::Open,
+    ')' | ']' | '}' | '”' | '’' | '»' | '」' | '』' | '）' | '］' | '｝' | '〕' | '〉' | '》' | '】' | '〗' | '〙' | '〛' | '｠' | '｣'
+      | ',' | '.' | ':' | ';' | '!' | '?' | '、' | '。' | '，' | '．' | '：' | '；' | '！' | '？' | '､' | '｡' | '‼' | '⁇' | '⁈' | '⁉' => Class::Close,
+    'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ゕ' | 'ゖ'
+      | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ' | 'ュ' | 'ョ' | 'ヮ' | 'ヵ' | 'ヶ' | 'ㇰ'..='ㇿ' | 'ｧ'..='ｯ'
+      | 'ー' | 'ｰ' | 'ゝ' | 'ゞ' | 'ヽ' | 'ヾ' | '々' | '〻' | '・' | '･' | '゠' | '〜' | '～' | '‐' | '–' => Class::NonStarter,
+    c if width(c) == 0 => Class::Combining,
+    _ => Class::Other,
+  }
+}
+
+// How many columns a character takes up, per UAX #11: two for wide and
+// full-width characters, none for combining marks and other zero-width
+// characters, and one for everything else, ambiguous ones included.
+pub fn width(c: char) -> usize {
+  match c as u32 {
+    0x0300..=0x036F | 0x200B..=0x200F | 0x2060..=0x2064 | 0x3099..=0x309A | 0xFE00..=0xFE0F | 0xFEFF => 0,
+    0x1100..=0x115F | 0x231A..=0x231B | 0x2329..=0x232A | 0x2E80..=0x303E | 0x3041..=0x33FF
+      | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xA960..=0xA97F | 0xAC00..=0xD7A3
+      | 0xF900..=0xFAFF | 0xFE10..=0xFE19 | 0xFE30..=0xFE6F | 0xFF01..=0xFF60 | 0xFFE0..=0xFFE6
+      | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x2FFFD | 0x30000..=0x3FFFD => 2,
+    _ => 1,
+  }
+}
+
+pub fn str_width(text: &str) -> usize {
+  text.chars().map(width).sum()
+}
+
+pub fn is_wide(c: char) -> bool {
+  width(c) == 2
+}
+
+pub fn is_full_stop(c: char) -> bool {
+  FULL_STOPS.contains(&c)
+}
+
+fn is_kanji(c: char) -> bool {
+  match c as u32 {
+    0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3FFFD => true,
+    _ => c == '々' || c == '〆' || c == 'ヶ' || c == '〇',
+  }
+}
+
+// Whether a line may break between two characters. Wide characters can be
+// broken between almost anywhere, but text made up of narrow ones, like
+// English, only breaks after spaces.
+pub fn can_break(before: char, after: char) -> bool {
+  match (class(before), class(after)) {
+    (_, Class::Space) | (_, Class::Close) | (_, Class::NonStarter) | (_, Class::Combining) => false,
+    (Class::Open, _)  => false,
+    (Class::Space, _) => true,
+    _                 => is_wide(before) || is_wide(after),
+  }
+}
+
+// Break `text` into lines at most `columns` wide, as ranges of its
+// characters, breaking only where `can_break` allows and never inside any of
+// `keep_together`. A line only runs over when there's nowhere to break it.
+// Spaces at a break are dropped.
+pub fn wrap(text: &str, columns: usize, keep_together: &[Range<usize>]) -> Vec<Range<usize>> {
+  let chars = text.chars().collect::<Vec<char>>();
+  let breakable = |at: usize| {
+    can_break(chars[at - 1], chars[at]) && !keep_together.iter().any(|range| range.start < at && at < range.end)
+  };
+
+  let mut lines = Vec::new();
+  let mut start = 0;
+  while start < chars.len() {
+    let mut end = start;
+    let mut line_width = 0;
+    let mut last_break = None;
+    while end < chars.len() {
+      if end > start && breakable(end) {
+        last_break = Some(end);
+      }
+      line_width += width(chars[end]);
+      if line_width > columns && !chars[end].is_whitespace() {
+        break;
+      }
+      end += 1;
+    }
+    if end < chars.len() {
+      end = last_break.unwrap_or_else(|| end.max(start + 1));
+    }
+
+    let mut trimmed = end;
+    while trimmed > start && chars[trimmed - 1].is_whitespace() {
+      trimmed -= 1;
+    }
+    lines.push(start..trimmed);
+
+    start = end;
+    while start < chars.len() && chars[start].is_whitespace() {
+      start += 1;
+    }
+  }
+
+  lines
+}
+
+// What goes between two lines joined into one paragraph: a space, except
+// where either side of the join is wide, since Japanese and Chinese don't put
+// spaces between words.
+pub fn separator(before: &str, after: &str) -> &'static str {
+  match (before.chars().last(), after.chars().next()) {
+    (Some(before), Some(after)) if !is_wide(before) && !is_wide(after) => " ",
+    _                                                                  => "",
+  }
+}
+
+pub fn join(lines: &[&str]) -> String {
+  let mut joined = String::new();
+  for line in lines {
+    let separator = separator(&joined, line);
+    joined.push_str(separator);
+    joined.push_str(line);
+  }
+  joined
+}
+
+// Take the ruby out of a line, leaving its text as it reads without them.
+// Anything that doesn't make a complete annotation is left as written.
+pub fn parse_ruby(line: &str) -> (String, Vec<Ruby>) {
+  let chars = line.chars().collect::<Vec<char>>();
+  let mut text = Vec::new();
+  let mut ruby = Vec::new();
+  let mut marked = None;
+
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    if c == RUBY_START && marked_annotation(&chars, i + 1).is_some() {
+      marked = Some(text.len());
+      i += 1;
+      continue;
+    }
+
+    let close = if c == RUBY_OPEN { annotation(&chars, i) } else { None };
+    let start = marked.unwrap_or_else(|| text.len() - text.iter().rev().take_while(|&&c| is_kanji(c)).count());
+    match close {
+      Some(close) if start < text.len() => {
+        ruby.push(Ruby {
+          start,
+          end: text.len(),
+          text: chars[i + 1..close].iter().collect(),
+        });
+        marked = None;
+        i = close + 1;
+      },
+      _ => {
+        text.push(c);
+        i += 1;
+      },
+    }
+  }
+
+  (text.into_iter().collect(), ruby)
+}
+
+// Where the annotation after a base marked from `from` closes. The base runs
+// up to the `《`, and can't be empty or take in spaces or punctuation.
+fn marked_annotation(chars: &[char], from: usize) -> Option<usize> {
+  let length = chars[from..]
+    .iter()
+    .take_while(|&&c| c != RUBY_START && !matches!(class(c), Class::Space | Class::Open | Class::Close))
+    .count();
+  if length > 0 { annotation(chars, from + length) } else { None }
+}
+
+// Where the annotation that opens at `open` closes, if it's complete and not
+// empty.
+fn annotation(chars: &[char], open: usize) -> Option<usize> {
+  if chars.get(open) != Some(&RUBY_OPEN) {
+    return None;
+  }
+  let close = open + chars[open..].iter().position(|&c| c == RUBY_CLOSE)?;
+  if close > open + 1 { Some(close) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ruby(start: usize, end: usize, text: &str) -> Ruby {
+    Ruby { start, end, text: text.to_string() }
+  }
+
+  #[test]
+  fn ruby_annotates_the_kanji_before_it() {
+    assert_eq!(parse_ruby("躍《おど》り出る"), ("躍り出る".to_string(), vec![ruby(0, 1, "おど")]));
+    assert_eq!(parse_ruby("まだ若《わか》い"), ("まだ若い".to_string(), vec![ruby(2, 3, "わか")]));
+  }
+
+  #[test]
+  fn a_full_width_bar_marks_where_the_base_starts() {
+    assert_eq!(
+      parse_ruby("あの｜東京タワー《とうきょうタワー》へ"),
+      ("あの東京タワーへ".to_string(), vec![ruby(2, 7, "とうきょうタワー")]));
+  }
+
+  #[test]
+  fn text_is_left_alone_unless_the_annotation_follows_the_base() {
+    for line in &["a | b《c》", "x|y《z》", "｜東京 タワー《とうきょう》", "｜《から》", "｜東京、タワー《とうきょう》"] {
+      assert_eq!(parse_ruby(line), (line.to_string(), vec![]), "{}", line);
+    }
+  }
+}
@@ -0,0 +1,133 @@
+use std::io;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+
+const DELIMITER: &str = "---";
+
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+  pub title: Option<String>,
+  pub slug: Option<String>,
+  pub series: Option<String>,
+  pub part: Option<u32>,
+  pub date: Option<DateTime<Utc>>,
+}
+
+// Split an optional block of `key: value` pairs, fenced by `---` lines, off
+// the top of a writing sample. Unknown keys are ignored.
+pub fn split(content: &str) -> Result<(FrontMatter, &str), io::Error> {
+  let mut front_matter = FrontMatter::default();
+
+  if !content.starts_with(DELIMITER) {
+    return Ok((front_matter, content));
+  }
+
+  let mut offset = 0;
+  let mut closed = false;
+  for (index, line) in content.split('\n').enumerate() {
+    offset += line.len() + 1;
+    let line = line.trim();
+
+    if index == 0 {
+      continue;
+    }
+    if line == DELIMITER {
+      closed = true;
+      break;
+    }
+    if line.is_empty() {
+      continue;
+    }
+
+    let mut pair = line.splitn(2, ':');
+    let key = pair.next().unwrap().trim();
+    let value = match pair.next() {
+      Some(value) => value.trim().to_string(),
+      None        => return Err(invalid(format!("Expected `key: value` in front matter, found `{}`", line))),
+    };
+
+    match key {
+      "title"  => front_matter.title = Some(value),
+      "slug"   => front_matter.slug = Some(value),
+      "series" => front_matter.series = Some(value),
+      "part"   => front_matter.part = Some(value
+        .parse()
+        .map_err(|_| invalid(format!("Front matter part must be a number, found `{}`", value)))?),
+      "date"   => front_matter.date = Some(parse_date(&value)?),
+      _        => (),
+    }
+  }
+
+  if !closed {
+    return Err(invalid("Front matter is missing its closing `---`".to_string()));
+  }
+
+  Ok((front_matter, &content[offset.min(content.len())..]))
+}
+
+// Dates may be written as `2016-09-12` or as a full RFC 3339 timestamp.
+fn parse_date(value: &str) -> Result<DateTime<Utc>, io::Error> {
+  if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+    return Ok(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()));
+  }
+
+  DateTime::parse_from_rfc3339(value)
+    .map(|date| date.with_timezone(&Utc))
+    .map_err(|_| invalid(format!("Front matter date must look like 2016-09-12, found `{}`", value)))
+}
+
+fn invalid(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn front_matter_is_split_off_the_body() {
+    let (front_matter, body) = split("---\ntitle: On Rust\nseries: Errors\n---\nBody.\n").unwrap();
+    assert_eq!(front_matter.title.as_deref(), Some("On Rust"));
+    assert_eq!(front_matter.series.as_deref(), Some("Errors"));
+    assert_eq!(body, "Body.\n");
+
+    let (front_matter, body) = split("No front matter.\n").unwrap();
+    assert!(front_matter.slug.is_none());
+    assert_eq!(body, "No front matter.\n");
+  }
+
+  #[test]
+  fn slugs_can_be_overridden() {
+    let (front_matter, _) = split("---\nslug: on-rust\n---\nBody.\n").unwrap();
+    assert_eq!(front_matter.slug.as_deref(), Some("on-rust"));
+    assert!(front_matter.title.is_none());
+  }
+
+  #[test]
+  fn front_matter_must_be_closed() {
+    let err = split("---\ntitle: On Rust\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("closing `---`"), "{}", err);
+  }
+
+  #[test]
+  fn parts_must_be_numbers() {
+    assert_eq!(split("---\npart: 2\n---\n").unwrap().0.part, Some(2));
+
+    let err = split("---\npart: two\n---\n").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("`two`"), "{}", err);
+  }
+
+  #[test]
+  fn dates_are_days_or_timestamps() {
+    let day = split("---\ndate: 2016-09-12\n---\n").unwrap().0.date.unwrap();
+    assert_eq!(day, Utc.with_ymd_and_hms(2016, 9, 12, 0, 0, 0).unwrap());
+
+    let timestamp = split("---\ndate: 2016-09-12T18:30:00-04:00\n---\n").unwrap().0.date.unwrap();
+    assert_eq!(timestamp, Utc.with_ymd_and_hms(2016, 9, 12, 22, 30, 0).unwrap());
+
+    assert!(split("---\ndate: 12/09/2016\n---\n").is_err());
+  }
+}
@@ -0,0 +1,353 @@
+mod cjk;
+mod front_matter;
+mod stats;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use i18n::Catalogs;
+
+pub use self::cjk::Ruby;
+pub use self::stats::{is_stop_word, Stats};
+
+
+// In columns, where wide characters like kanji take up two.
+const EXCERPT_WIDTH: usize = 280;
+// Samples are hard-wrapped by hand, the English ones well within this. It
+// catches paragraphs written on one line, as Japanese often is.
+const LINE_WIDTH: usize = 100;
+
+
+#[derive(Debug, Serialize)]
+pub struct Line {
+  pub text: String,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub ruby: Vec<Ruby>,
+}
+
+// A stretch of a line, with the reading it's annotated with, if any.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Run {
+  pub text: String,
+  pub ruby: Option<String>,
+}
+
+impl Line {
+  pub fn runs(&self) -> Vec<Run> {
+    let chars = self.text.chars().collect::<Vec<char>>();
+    let mut runs = Vec::new();
+    let plain = |start: usize, end: usize, runs: &mut Vec<Run>| if start < end {
+      runs.push(Run { text: chars[start..end].iter().collect(), ruby: None });
+    };
+
+    let mut offset = 0;
+    for ruby in &self.ruby {
+      plain(offset, ruby.start, &mut runs);
+      runs.push(Run {
+        text: chars[ruby.start..ruby.end].iter().collect(),
+        ruby: Some(ruby.text.clone()),
+      });
+      offset = ruby.end;
+    }
+    plain(offset, chars.len(), &mut runs);
+
+    runs
+  }
+
+  // A line indented by a full-width space starts a paragraph in Japanese.
+  fn is_indented(&self) -> bool {
+    self.text.starts_with(cjk::IDEOGRAPHIC_SPACE)
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sample {
+  #[serde(rename = "fileName")]
+  pub file_name: String,
+  pub slug: String,
+  pub title: String,
+  pub series: String,
+  pub part: u32,
+  pub updated: DateTime<Utc>,
+  pub lines: Vec<Line>,
+  pub stats: Stats,
+  // The language of a translation. Originals are in the site's language.
+  #[serde(skip)]
+  pub language: Option<String>,
+  #[serde(skip)]
+  pub translations: Vec<Sample>,
+}
+
+pub type CollectResult = Result<Vec<Sample>, io::Error>;
+
+impl Sample {
+  // This sample translated into `language`, or as written if it hasn't been.
+  pub fn in_language(&self, language: &str) -> &Sample {
+    self.translations
+      .iter()
+      .find(|translation| translation.language.as_deref() == Some(language))
+      .unwrap_or(self)
+  }
+
+  // Group hard-wrapped lines into paragraphs, which are broken by blank
+  // lines or by a full-width indent.
+  fn paragraph_lines(&self) -> Vec<Vec<&Line>> {
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+
+    for line in &self.lines {
+      if (line.text.is_empty() || line.is_indented()) && !current.is_empty() {
+        paragraphs.push(current);
+        current = Vec::new();
+      }
+      if !line.text.is_empty() {
+        current.push(line);
+      }
+    }
+    if !current.is_empty() {
+      paragraphs.push(current);
+    }
+
+    paragraphs
+  }
+
+  // Join hard-wrapped lines back together. The indent is left to the page.
+  pub fn paragraphs(&self) -> Vec<String> {
+    self.paragraph_lines()
+      .into_iter()
+      .map(|lines| {
+        let texts = lines.iter().map(|line| line.text.as_str()).collect::<Vec<&str>>();
+        cjk::join(&texts).trim_start_matches(cjk::IDEOGRAPHIC_SPACE).to_string()
+      })
+      .collect()
+  }
+
+  // Paragraphs as runs of text, so that ruby can be set over the text it
+  // annotates.
+  pub fn annotated_paragraphs(&self) -> Vec<Vec<Run>> {
+    self.paragraph_lines()
+      .into_iter()
+      .map(|lines| {
+        let mut runs = Vec::new();
+        let mut previous: Option<&Line> = None;
+        for line in lines {
+          if let Some(previous) = previous {
+            let separator = cjk::separator(&previous.text, &line.text);
+            if !separator.is_empty() {
+              runs.push(Run { text: separator.to_string(), ruby: None });
+            }
+          }
+          runs.extend(line.runs());
+          previous = Some(line);
+        }
+        if let Some(first) = runs.first_mut() {
+          first.text = first.text.trim_start_matches(cjk::IDEOGRAPHIC_SPACE).to_string();
+        }
+        runs.retain(|run| !run.text.is_empty());
+        runs
+      })
+      .collect()
+  }
+
+  // The opening of the sample, cut where a line could break.
+  pub fn excerpt(&self) -> String {
+    let first = self.paragraphs().into_iter().next().unwrap_or_default();
+    if cjk::str_width(&first) <= EXCERPT_WIDTH {
+      return first;
+    }
+
+    let cut = cjk::wrap(&first, EXCERPT_WIDTH, &[])
+      .into_iter()
+      .next()
+      .map(|line| first.chars().take(line.end).collect::<String>())
+      .unwrap_or_default();
+
+    format!("{}…", cut.trim_end_matches(|c: char| !c.is_alphanumeric()))
+  }
+}
+
+// Samples in `directory`, with any translations into the catalogs' languages
+// attached to the sample they translate.
+pub fn collect<P: AsRef<Path>>(directory: P, catalogs: &Catalogs) -> CollectResult {
+  let dir_contents = fs::read_dir(directory.as_ref())?;
+  let sources = dir_contents
+    .filter(Result::is_ok)
+    .map(Result::unwrap)
+    .map(|file| file.path())
+    .filter(|path| path.is_file());
+
+  let mut samples = Vec::new();
+  let mut translations = Vec::new();
+  for source in sources {
+    let file_name = source
+      .file_name()
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .to_string();
+    let stem = source
+      .file_stem()
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .to_string();
+    let modified = fs::metadata(&source)?.modified()?;
+    let mut content = String::new();
+    fs::File::open(source)?.read_to_string(&mut content)?;
+
+    let (front_matter, body) = front_matter::split(&content)?;
+    let (stem, language) = split_language(&stem, catalogs);
+    let (stem_series, stem_part) = split_series(stem);
+    let slug = slugify(front_matter.slug.as_deref().unwrap_or(stem));
+    let series = slugify(front_matter.series.as_deref().unwrap_or(stem_series));
+    let part = front_matter.part.unwrap_or(stem_part);
+    let title = front_matter.title.unwrap_or_else(|| humanize(stem_series, part));
+    let updated = front_matter.date.unwrap_or_else(|| DateTime::from(modified));
+
+    let lines = body
+      .split("\n")
+      .flat_map(read_line)
+      .collect::<Vec<Line>>();
+    let text = lines.iter().map(|line| line.text.as_str()).collect::<Vec<&str>>().join("\n");
+    let stats = stats::analyze(&text);
+    let sample = Sample{ file_name, slug, title, series, part, updated, lines, stats, language: None, translations: Vec::new() };
+    match language {
+      Some(language) => translations.push((stem.to_string(), Sample { language: Some(language), ..sample })),
+      None           => samples.push((stem.to_string(), sample)),
+    }
+  }
+
+  // A translation is found at the same URL, and in the same place in its
+  // series, as the sample it translates.
+  for (stem, translation) in translations {
+    let original = samples
+      .iter_mut()
+      .find(|(original_stem, _)| *original_stem == stem)
+      .map(|(_, original)| original)
+      .ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} is a translation of {}, which doesn't exist", translation.file_name, stem)))?;
+    original.translations.push(Sample {
+      slug: original.slug.clone(),
+      series: original.series.clone(),
+      part: original.part,
+      ..translation
+    });
+  }
+
+  // Samples are found by slug, so no two can share one.
+  let mut slugs = HashMap::new();
+  for (_, sample) in &samples {
+    if let Some(other) = slugs.insert(sample.slug.as_str(), sample.file_name.as_str()) {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} and {} both have the slug `{}`", other, sample.file_name, sample.slug)));
+    }
+  }
+
+  let mut samples = samples.into_iter().map(|(_, sample)| sample).collect::<Vec<Sample>>();
+  samples.sort_by(|a, b| a.series.cmp(&b.series).then(a.part.cmp(&b.part)));
+  Ok(samples)
+}
+
+// A line of a sample as it's written, with its ruby taken out, and wrapped
+// if it's too wide. A full-width indent is kept, since it starts a paragraph.
+fn read_line(line: &str) -> Vec<Line> {
+  let line = line
+    .trim_end()
+    .trim_start_matches(|c: char| c.is_whitespace() && c != cjk::IDEOGRAPHIC_SPACE);
+  let (text, ruby) = cjk::parse_ruby(line);
+  if text.is_empty() {
+    return vec![Line { text, ruby }];
+  }
+
+  let chars = text.chars().collect::<Vec<char>>();
+  let bases = ruby.iter().map(|ruby| ruby.start..ruby.end).collect::<Vec<Range<usize>>>();
+  cjk::wrap(&text, LINE_WIDTH, &bases)
+    .into_iter()
+    .map(|range| Line {
+      text: chars[range.clone()].iter().collect(),
+      ruby: ruby
+        .iter()
+        .filter(|ruby| range.start <= ruby.start && ruby.end <= range.end)
+        .map(|ruby| Ruby {
+          start: ruby.start - range.start,
+          end: ruby.end - range.start,
+          text: ruby.text.clone(),
+        })
+        .collect(),
+    })
+    .collect()
+}
+
+// Find the samples immediately before and after the given one in its series.
+pub fn neighbours<'a>(samples: &'a [Sample], sample: &Sample) -> (Option<&'a Sample>, Option<&'a Sample>) {
+  let series = samples
+    .iter()
+    .filter(|other| other.series == sample.series)
+    .collect::<Vec<&Sample>>();
+
+  match series.iter().position(|other| other.slug == sample.slug) {
+    Some(i) => (
+      if i > 0 { Some(series[i - 1]) } else { None },
+      series.get(i + 1).cloned(),
+    ),
+    None    => (None, None),
+  }
+}
+
+fn slugify(text: &str) -> String {
+  text
+    .to_lowercase()
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .collect::<Vec<&str>>()
+    .join("-")
+}
+
+// Translations are named for the sample they translate and one of the
+// catalogs' languages, like `fp-and-nlp.ja.txt`. Anything else after a dot,
+// like `notes.old.txt`, is just part of the name.
+fn split_language<'a>(stem: &'a str, catalogs: &Catalogs) -> (&'a str, Option<String>) {
+  let dot = match stem.rfind('.') {
+    Some(dot) => dot,
+    None      => return (stem, None),
+  };
+
+  match catalogs.supported(&stem[dot + 1..]) {
+    Some(language) => (&stem[..dot], Some(language.to_string())),
+    None           => (stem, None),
+  }
+}
+
+// Samples in a series are named like `fp-and-nlp.txt`, `fp-and-nlp2.txt`, ...
+fn split_series(stem: &str) -> (&str, u32) {
+  let series = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+  let part = stem[series.len()..].parse().unwrap_or(1);
+
+  (series.trim_end_matches(&['-', '_'][..]), part)
+}
+
+fn humanize(series: &str, part: u32) -> String {
+  let words = series
+    .split(&['-', '_'][..])
+    .filter(|word| !word.is_empty())
+    .collect::<Vec<&str>>()
+    .join(" ");
+  let mut chars = words.chars();
+  let title = match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+    None        => String::new(),
+  };
+
+  if part > 1 {
+    format!("{} (part {})", title, part)
+  } else {
+    title
+  }
+}
@@ -0,0 +1,231 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use super::cjk;
+
+
+const WORDS_PER_MINUTE: usize = 200;
+const CHARACTERS_PER_MINUTE: usize = 500;
+const TOP_TERM_COUNT: usize = 5;
+
+pub const STOP_WORDS: &[&str] = &[
+  "a", "about", "above", "after", "again", "against", "all", "also", "am", "an",
+  "and", "any", "are", "as", "at", "be", "because", "been", "before", "being",
+  "below", "between", "both", "but", "by", "can", "could", "did", "do", "does",
+  "doing", "down", "during", "each", "either", "even", "few", "for", "from",
+  "further", "had", "has", "have", "having", "he", "her", "here", "hers",
+  "herself", "him", "himself", "his", "how", "i", "if", "in", "into", "is",
+  "it", "its", "itself", "just", "let", "like", "may", "me", "might", "more",
+  "most", "much", "must", "my", "myself", "no", "nor", "not", "now", "of",
+  "off", "on", "once", "one", "only", "or", "other", "our", "ours",
+  "ourselves", "out", "over", "own", "same", "she", "should", "so", "some",
+  "such", "than", "that", "the", "their", "theirs", "them", "themselves",
+  "then", "there", "these", "they", "this", "those", "through", "to", "too",
+  "under", "until", "up", "us", "very", "was", "way", "we", "were", "what",
+  "when", "where", "which", "while", "who", "whom", "why", "will", "with",
+  "would", "you", "your", "yours", "yourself", "yourselves",
+];
+
+#[derive(Debug, Serialize)]
+pub struct Term {
+  pub term: String,
+  pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+  #[serde(rename = "wordCount")]
+  pub word_count: usize,
+  // Japanese and Chinese aren't written in words, so their characters are
+  // counted instead.
+  #[serde(rename = "characterCount")]
+  pub character_count: usize,
+  #[serde(rename = "sentenceCount")]
+  pub sentence_count: usize,
+  #[serde(rename = "readingMinutes")]
+  pub reading_minutes: usize,
+  #[serde(rename = "readingEase")]
+  pub reading_ease: f64,
+  #[serde(rename = "topTerms")]
+  pub top_terms: Vec<Term>,
+}
+
+pub fn is_stop_word(word: &str) -> bool {
+  STOP_WORDS.binary_search(&word).is_ok()
+}
+
+pub fn analyze(text: &str) -> Stats {
+  let tokens = text
+    .split_whitespace()
+    .filter(|token| token.chars().any(char::is_alphanumeric))
+    .collect::<Vec<&str>>();
+
+  let words = text
+    .split(|c: char| c.is_whitespace() || cjk::is_wide(c))
+    .filter(|token| token.chars().any(char::is_alphanumeric))
+    .map(normalize)
+    .collect::<Vec<String>>();
+  let word_count = words.len();
+  let character_count = text
+    .chars()
+    .filter(|&c| cjk::is_wide(c) && c.is_alphanumeric())
+    .count();
+
+  let sentence_ends = tokens
+    .iter()
+    .filter(|token| ends_sentence(token))
+    .count();
+  let full_stops = text
+    .chars()
+    .filter(|&c| cjk::is_full_stop(c))
+    .count();
+  let trailing_fragment = tokens
+    .last()
+    .map(|token| !ends_sentence(token) && !token.trim_end_matches(is_closing).ends_with(cjk::is_full_stop))
+    .unwrap_or(false);
+  let sentence_count = sentence_ends + full_stops + if trailing_fragment { 1 } else { 0 };
+
+  let syllable_count = words
+    .iter()
+    .map(|word| syllables(word))
+    .sum::<usize>();
+  // The score is only meaningful for English.
+  let reading_ease = if character_count > word_count {
+    0.0
+  } else {
+    reading_ease(word_count, sentence_count - full_stops, syllable_count)
+  };
+
+  Stats {
+    word_count,
+    character_count,
+    sentence_count,
+    reading_minutes: reading_minutes(word_count, character_count),
+    reading_ease,
+    top_terms: top_terms(&words),
+  }
+}
+
+// Lowercase a token and strip any punctuation surrounding it, keeping
+// internal apostrophes and hyphens as in "doesn't" or "toe-to-toe".
+fn normalize(token: &str) -> String {
+  token
+    .trim_matches(|c: char| !c.is_alphanumeric())
+    .to_lowercase()
+}
+
+fn ends_sentence(token: &str) -> bool {
+  token
+    .trim_end_matches(&['"', '\'', ')'][..])
+    .ends_with(&['.', '!', '?'][..])
+}
+
+fn is_closing(c: char) -> bool {
+  "\"')」』）".contains(c)
+}
+
+fn reading_minutes(word_count: usize, character_count: usize) -> usize {
+  let minutes = word_count as f64 / WORDS_PER_MINUTE as f64 + character_count as f64 / CHARACTERS_PER_MINUTE as f64;
+  minutes.ceil() as usize
+}
+
+// The Flesch reading-ease score. Higher scores indicate easier text, with
+// most plain English landing somewhere between 60 and 70.
+fn reading_ease(words: usize, sentences: usize, syllables: usize) -> f64 {
+  if words == 0 || sentences == 0 {
+    return 0.0;
+  }
+
+  let words_per_sentence = words as f64 / sentences as f64;
+  let syllables_per_word = syllables as f64 / words as f64;
+  let score = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+
+  (score * 10.0).round() / 10.0
+}
+
+// Estimate syllables by counting groups of consecutive vowels, discounting
+// a silent trailing 'e'. Good enough for a readability estimate.
+fn syllables(word: &str) -> usize {
+  let mut count = 0;
+  let mut previous_was_vowel = false;
+
+  for c in word.chars() {
+    let is_vowel = "aeiouy".contains(c);
+    if is_vowel && !previous_was_vowel {
+      count += 1;
+    }
+    previous_was_vowel = is_vowel;
+  }
+
+  if count > 1 && word.ends_with('e') && !word.ends_with("le") {
+    count -= 1;
+  }
+
+  cmp::max(1, count)
+}
+
+fn top_terms(words: &[String]) -> Vec<Term> {
+  let mut counts = HashMap::new();
+  for word in words {
+    if word.chars().count() > 2 && !is_stop_word(word) && !word.chars().all(char::is_numeric) {
+      *counts.entry(word.as_str()).or_insert(0) += 1;
+    }
+  }
+
+  let mut terms = counts
+    .into_iter()
+    .map(|(term, count)| Term {
+      term: term.to_string(),
+      count,
+    })
+    .collect::<Vec<Term>>();
+  terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+  terms.truncate(TOP_TERM_COUNT);
+
+  terms
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stop_words_stay_sorted() {
+    assert!(STOP_WORDS.windows(2).all(|w| w[0] < w[1]));
+    assert!(STOP_WORDS.iter().all(|word| is_stop_word(word)));
+    assert!(!is_stop_word("rust"));
+  }
+
+  #[test]
+  fn syllables_are_counted_by_vowel_groups() {
+    let counts = &[
+      ("cat", 1),
+      ("the", 1),
+      ("code", 1),
+      ("table", 2),
+      ("rhythm", 1),
+      ("beautiful", 3),
+      ("", 1),
+    ];
+    for (word, count) in counts {
+      assert_eq!(syllables(word), *count, "{}", word);
+    }
+  }
+
+  #[test]
+  fn reading_ease_follows_the_flesch_formula() {
+    assert_eq!(reading_ease(10, 1, 10), 112.1);
+    assert_eq!(reading_ease(20, 2, 30), 69.8);
+    assert_eq!(reading_ease(0, 1, 0), 0.0);
+    assert_eq!(reading_ease(10, 0, 10), 0.0);
+  }
+
+  #[test]
+  fn reading_minutes_round_up() {
+    assert_eq!(reading_minutes(0, 0), 0);
+    assert_eq!(reading_minutes(200, 0), 1);
+    assert_eq!(reading_minutes(201, 0), 2);
+    assert_eq!(reading_minutes(0, 500), 1);
+    assert_eq!(reading_minutes(100, 250), 1);
+  }
+}